@@ -0,0 +1,233 @@
+// src/cells.rs - Character cell metrics for converting pixels to columns/rows
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CellSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+// Fallbacks when nothing better is configured, calibrated or detected
+pub fn builtin(backend: &str) -> CellSize {
+    match backend {
+        "wt" => CellSize { width: 9, height: 19 },
+        "terminal" => CellSize { width: 7, height: 16 },
+        _ => CellSize { width: 8, height: 16 },
+    }
+}
+
+// Calibrated sizes, one "backend<TAB>width<TAB>height" line each
+pub fn load_calibrated(path: &Path) -> HashMap<String, CellSize> {
+    let content = fs::read_to_string(path).unwrap_or_default();
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split('\t');
+            let backend = parts.next()?.to_string();
+            let width = parts.next()?.trim().parse().ok()?;
+            let height = parts.next()?.trim().parse().ok()?;
+            Some((backend, CellSize { width, height }))
+        })
+        .collect()
+}
+
+pub fn save_calibrated(path: &Path, backend: &str, cell: CellSize) -> io::Result<()> {
+    let mut sizes = load_calibrated(path);
+    sizes.insert(backend.to_string(), cell);
+
+    let mut content = String::new();
+    for (name, size) in &sizes {
+        content.push_str(&format!("{}\t{}\t{}\n", name, size.width, size.height));
+    }
+    fs::write(path, content)
+}
+
+// Approximate a cell from a font size in points at 96 DPI: monospace glyphs
+// are about 0.6em wide and terminals add roughly 25% line spacing.
+fn from_points(points: f32) -> Option<CellSize> {
+    if points <= 0.0 {
+        return None;
+    }
+    let pixels = points * 96.0 / 72.0;
+    Some(CellSize {
+        width: (pixels * 0.6).round().max(1.0) as u32,
+        height: (pixels * 1.25).round().max(1.0) as u32,
+    })
+}
+
+fn home_dir() -> Option<PathBuf> {
+    env::var_os("HOME").or_else(|| env::var_os("USERPROFILE")).map(PathBuf::from)
+}
+
+fn xdg_config_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| home_dir().map(|home| home.join(".config")))
+}
+
+// First number after `key` (and any of ` :="`) in `text`
+fn number_after(text: &str, key: &str) -> Option<f32> {
+    let start = text.find(key)? + key.len();
+    let rest = text[start..].trim_start_matches([' ', '\t', ':', '=', '"']);
+    let end = rest
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(rest.len());
+    rest[..end].parse().ok()
+}
+
+// Read the font size from the terminal's own config file, where it has one
+pub fn detect_from_font(backend: &str) -> Option<CellSize> {
+    let points = match backend {
+        "kitty" => {
+            let conf = fs::read_to_string(xdg_config_dir()?.join("kitty").join("kitty.conf")).ok()?;
+            conf.lines()
+                .map(str::trim)
+                .find(|line| line.starts_with("font_size"))
+                .and_then(|line| number_after(line, "font_size"))?
+        }
+        "alacritty" => {
+            let dir = xdg_config_dir()?.join("alacritty");
+            let conf = fs::read_to_string(dir.join("alacritty.toml"))
+                .or_else(|_| fs::read_to_string(dir.join("alacritty.yml")))
+                .ok()?;
+            // `size` under the [font] table (or `font:` block in YAML)
+            let font = &conf[conf.find("font")?..];
+            font.lines()
+                .map(str::trim)
+                .find(|line| line.starts_with("size"))
+                .and_then(|line| number_after(line, "size"))?
+        }
+        "wt" => {
+            let settings = PathBuf::from(env::var_os("LOCALAPPDATA")?)
+                .join("Packages")
+                .join("Microsoft.WindowsTerminal_8wekyb3d8bbwe")
+                .join("LocalState")
+                .join("settings.json");
+            let json = fs::read_to_string(settings).ok()?;
+            number_after(&json, "\"fontSize\"").or_else(|| {
+                let font = &json[json.find("\"font\"")?..];
+                number_after(font, "\"size\"")
+            })?
+        }
+        _ => return None,
+    };
+    from_points(points)
+}
+
+// Guess which backend the current terminal is, for `rund --calibrate`
+pub fn current_backend() -> Option<String> {
+    let has = |var: &str| env::var_os(var).is_some();
+    let name = if has("WT_SESSION") {
+        "wt"
+    } else if cfg!(target_os = "windows") {
+        "cmd"
+    } else if has("KITTY_WINDOW_ID") {
+        "kitty"
+    } else if has("ALACRITTY_SOCKET") || has("ALACRITTY_WINDOW_ID") || has("ALACRITTY_LOG") {
+        "alacritty"
+    } else if has("KONSOLE_VERSION") {
+        "konsole"
+    } else if has("VTE_VERSION") {
+        "gnome-terminal"
+    } else if has("XTERM_VERSION") {
+        "xterm"
+    } else if env::var("TERM_PROGRAM").as_deref() == Ok("Apple_Terminal") {
+        "terminal"
+    } else {
+        return None;
+    };
+    Some(name.to_string())
+}
+
+// Measure the cell size of the terminal rund is running in: the window's
+// pixel size divided by its columns and rows, as reported by TIOCGWINSZ.
+#[cfg(not(target_os = "windows"))]
+pub fn measure_current() -> io::Result<CellSize> {
+    use std::os::raw::{c_int, c_ulong};
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct WinSize {
+        ws_row: u16,
+        ws_col: u16,
+        ws_xpixel: u16,
+        ws_ypixel: u16,
+    }
+
+    #[cfg(target_os = "macos")]
+    const TIOCGWINSZ: c_ulong = 0x40087468;
+    #[cfg(not(target_os = "macos"))]
+    const TIOCGWINSZ: c_ulong = 0x5413;
+
+    extern "C" {
+        fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
+    }
+
+    let mut size = WinSize::default();
+    if unsafe { ioctl(1, TIOCGWINSZ, &mut size as *mut WinSize) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    if size.ws_col == 0 || size.ws_row == 0 || size.ws_xpixel == 0 || size.ws_ypixel == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "this terminal does not report its size in pixels",
+        ));
+    }
+
+    Ok(CellSize {
+        width: (size.ws_xpixel / size.ws_col) as u32,
+        height: (size.ws_ypixel / size.ws_row) as u32,
+    })
+}
+
+// conhost knows the font it renders with; under Windows Terminal this is
+// only ConPTY's idea of it, so calibrating wt may still need manual values.
+#[cfg(target_os = "windows")]
+pub fn measure_current() -> io::Result<CellSize> {
+    use std::ffi::c_void;
+
+    #[repr(C)]
+    struct Coord {
+        x: i16,
+        y: i16,
+    }
+
+    #[repr(C)]
+    struct ConsoleFontInfoEx {
+        cb_size: u32,
+        n_font: u32,
+        dw_font_size: Coord,
+        font_family: u32,
+        font_weight: u32,
+        face_name: [u16; 32],
+    }
+
+    const STD_OUTPUT_HANDLE: u32 = -11i32 as u32;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetStdHandle(std_handle: u32) -> *mut c_void;
+        fn GetCurrentConsoleFontEx(output: *mut c_void, maximum_window: i32, info: *mut ConsoleFontInfoEx) -> i32;
+    }
+
+    let mut info: ConsoleFontInfoEx = unsafe { std::mem::zeroed() };
+    info.cb_size = std::mem::size_of::<ConsoleFontInfoEx>() as u32;
+    if unsafe { GetCurrentConsoleFontEx(GetStdHandle(STD_OUTPUT_HANDLE), 0, &mut info) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    if info.dw_font_size.x <= 0 || info.dw_font_size.y <= 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "this console does not report its font size",
+        ));
+    }
+
+    Ok(CellSize {
+        width: info.dw_font_size.x as u32,
+        height: info.dw_font_size.y as u32,
+    })
+}
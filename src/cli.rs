@@ -0,0 +1,548 @@
+// src/cli.rs - Command line parsing
+use std::path::PathBuf;
+
+use crate::notify::Notify;
+use crate::config::{Config, PauseBehavior};
+use crate::launch::{GeometryOverride, RunOptions};
+use crate::{environment, shell};
+
+// What rund was asked to do
+#[derive(Debug)]
+pub enum Invocation {
+    Run {
+        options: Box<RunOptions>,
+        // None = default_app from config
+        app: Option<String>,
+        args: Vec<String>,
+    },
+    Help,
+    ConfigPath,
+    Monitors,
+    Calibrate(Option<String>),
+    // Back up the given files now, or list the backups without any
+    Backup(Vec<PathBuf>),
+    // List past launches, newest first, optionally only those matching
+    History { grep: Option<String>, limit: usize },
+    // Replay the Nth most recent launch
+    Again(usize),
+    // Serve launches over a local socket
+    Daemon,
+    // Running sessions: list them, end one, raise one's window
+    Ps,
+    Kill(String),
+    Focus(String),
+    // Start a layout file's popups, or close those a layout started
+    LayoutUp(String),
+    LayoutDown(Option<String>),
+    // Internal: run by the popup's wrapper on exit (remember_geometry)
+    RecordGeometry(String),
+    // Internal: run by the popup's wrapper on exit, for the history
+    RecordExit { id: String, code: Option<i64> },
+    // Internal: the loop a --watch popup runs instead of the command
+    WatchLoop { globs: Vec<String>, argv: Vec<String> },
+}
+
+// Subcommands are only recognized as the first argument; `rund run config`
+// or `rund -- config` runs an app with such a name instead
+pub fn parse(args: &[String]) -> Result<Invocation, String> {
+    let rest = args.get(1..).unwrap_or_default();
+    match args.first().map(String::as_str) {
+        Some("run") => parse_run(rest),
+        // `rund qf APP < errors.txt` is `rund --from-quickfix APP`
+        Some("qf") => match parse_run(rest)? {
+            Invocation::Run { mut options, app, args } => {
+                options.from_quickfix = true;
+                Ok(Invocation::Run { options, app, args })
+            }
+            other => Ok(other),
+        },
+        Some("help") => no_more(rest, Invocation::Help),
+        Some("config" | "--config") => no_more(rest, Invocation::ConfigPath),
+        Some("monitors" | "--monitors") => no_more(rest, Invocation::Monitors),
+        Some("calibrate" | "--calibrate") => match rest {
+            [] => Ok(Invocation::Calibrate(None)),
+            [backend] => Ok(Invocation::Calibrate(Some(backend.clone()))),
+            [_, extra, ..] => Err(unexpected(extra)),
+        },
+        Some("backup") => Ok(Invocation::Backup(rest.iter().map(PathBuf::from).collect())),
+        Some("--record-geometry") => match rest {
+            [app] => Ok(Invocation::RecordGeometry(app.clone())),
+            _ => Err("--record-geometry requires an app name".to_string()),
+        },
+        Some("history") => parse_history(rest),
+        Some("ps") => no_more(rest, Invocation::Ps),
+        Some("daemon") => no_more(rest, Invocation::Daemon),
+        Some(command @ ("kill" | "focus")) => match rest {
+            [target] if command == "kill" => Ok(Invocation::Kill(target.clone())),
+            [target] => Ok(Invocation::Focus(target.clone())),
+            [] => Err(format!("{} requires a session (number, id, title or file from rund ps)", command)),
+            [_, extra, ..] => Err(unexpected(extra)),
+        },
+        Some("layout") => match rest {
+            [action, file] if action == "up" => Ok(Invocation::LayoutUp(file.clone())),
+            [action] if action == "up" => Err("layout up requires a layout file".to_string()),
+            [action] if action == "down" => Ok(Invocation::LayoutDown(None)),
+            [action, file] if action == "down" => Ok(Invocation::LayoutDown(Some(file.clone()))),
+            [_, _, extra, ..] => Err(unexpected(extra)),
+            _ => Err("layout requires up FILE or down [FILE]".to_string()),
+        },
+        Some("again") => match rest {
+            [] => Ok(Invocation::Again(1)),
+            [n] => match n.parse() {
+                Ok(n) if n > 0 => Ok(Invocation::Again(n)),
+                _ => Err(invalid("again", n, "a launch number from rund history")),
+            },
+            [_, extra, ..] => Err(unexpected(extra)),
+        },
+        // `--record-exit ID CODE`; the code is empty when the shell had none
+        Some("--record-exit") => match rest {
+            [id, code] => Ok(Invocation::RecordExit { id: id.clone(), code: code.trim().parse().ok() }),
+            _ => Err("--record-exit requires a launch id and an exit code".to_string()),
+        },
+        // `--watch-loop GLOB... -- PROGRAM ARGS...`
+        Some("--watch-loop") => match rest.iter().position(|arg| arg == "--") {
+            Some(split) if split > 0 && split + 1 < rest.len() => Ok(Invocation::WatchLoop {
+                globs: rest[..split].to_vec(),
+                argv: rest[split + 1..].to_vec(),
+            }),
+            _ => Err("--watch-loop requires globs, then -- and the command".to_string()),
+        },
+        _ => parse_run(args),
+    }
+}
+
+// How much -v, -vv or --verbose asks for, or None for other arguments
+fn verbose_flag(arg: &str) -> Option<u8> {
+    match arg {
+        "--verbose" => Some(1),
+        _ => arg.strip_prefix('-').filter(|vs| !vs.is_empty() && vs.chars().all(|c| c == 'v')).map(|vs| vs.len() as u8),
+    }
+}
+
+// Verbosity flags before the command (`rund -v layout up dev`), and the
+// arguments after them
+pub fn verbosity(args: &[String]) -> (u8, &[String]) {
+    let count = args.iter().take_while(|arg| verbose_flag(arg).is_some()).count();
+    let verbosity = args[..count].iter().filter_map(|arg| verbose_flag(arg)).fold(0u8, u8::saturating_add);
+    (verbosity, &args[count..])
+}
+
+fn no_more(rest: &[String], invocation: Invocation) -> Result<Invocation, String> {
+    match rest.first() {
+        Some(extra) => Err(unexpected(extra)),
+        None => Ok(invocation),
+    }
+}
+
+fn unexpected(arg: &str) -> String {
+    format!("Unexpected argument '{}' (run rund --help for usage)", arg)
+}
+
+// Value of an option given as `--name value` or `--name=value`
+fn value(args: &[String], i: &mut usize, name: &str, inline: Option<&str>) -> Result<String, String> {
+    if let Some(inline) = inline {
+        return Ok(inline.to_string());
+    }
+    *i += 1;
+    args.get(*i).cloned().ok_or_else(|| format!("{} requires a value", name))
+}
+
+fn invalid(name: &str, value: &str, expected: &str) -> String {
+    format!("Invalid value '{}' for {}: expected {}", value, name, expected)
+}
+
+// `history [--grep PATTERN] [-n COUNT]`
+fn parse_history(args: &[String]) -> Result<Invocation, String> {
+    let mut grep = None;
+    let mut limit = 20;
+    let mut i = 0;
+    while i < args.len() {
+        let (name, inline) = match args[i].split_once('=') {
+            Some((name, inline)) if name.starts_with("--") => (name, Some(inline)),
+            _ => (args[i].as_str(), None),
+        };
+        match name {
+            "--grep" => grep = Some(value(args, &mut i, name, inline)?),
+            "-n" | "--count" => {
+                let v = value(args, &mut i, name, inline)?;
+                limit = v.parse().map_err(|_| invalid(name, &v, "a number of launches"))?;
+            }
+            "--all" => limit = usize::MAX,
+            _ => return Err(unexpected(&args[i])),
+        }
+        i += 1;
+    }
+    Ok(Invocation::History { grep, limit })
+}
+
+// rund's own options come before the app; everything from the app on (or
+// after `--`) is passed to it untouched
+fn parse_run(args: &[String]) -> Result<Invocation, String> {
+    let mut options = RunOptions::default();
+    let mut i = 0;
+
+    while i < args.len() {
+        let arg = args[i].as_str();
+        if arg == "--" {
+            i += 1;
+            break;
+        }
+        if !arg.starts_with('-') || arg == "-" {
+            break;
+        }
+
+        let (name, inline) = match arg.split_once('=') {
+            Some((name, inline)) if name.starts_with("--") => (name, Some(inline)),
+            _ => (arg, None),
+        };
+        let flag = |set: &mut dyn FnMut()| match inline {
+            Some(_) => Err(format!("{} does not take a value", name)),
+            None => {
+                set();
+                Ok(())
+            }
+        };
+
+        if let Some(n) = verbose_flag(arg) {
+            options.verbose = options.verbose.saturating_add(n);
+            i += 1;
+            continue;
+        }
+
+        match name {
+            "-h" | "--help" => return Ok(Invocation::Help),
+            "-t" | "--top" => flag(&mut || options.always_on_top = true)?,
+            "-c" | "--clipboard" => flag(&mut || options.use_clipboard = true)?,
+            "--no-pause" => flag(&mut || options.pause = Some(PauseBehavior::Never))?,
+            "--from-quickfix" => flag(&mut || options.from_quickfix = true)?,
+            "--first" => flag(&mut || options.first = true)?,
+            "--pause" => match inline {
+                Some(mode) => {
+                    options.pause = Some(PauseBehavior::parse(mode).ok_or_else(|| {
+                        invalid(name, mode, "never, always, auto, on_error or on_error_or_fast")
+                    })?)
+                }
+                // `--pause on_error app` takes the mode; a bare --pause
+                // before the app means always
+                None => match args.get(i + 1).and_then(|next| PauseBehavior::parse(next)) {
+                    Some(mode) => {
+                        options.pause = Some(mode);
+                        i += 1;
+                    }
+                    None => options.pause = Some(PauseBehavior::Always),
+                },
+            },
+            "--notify" => match inline {
+                Some(when) => {
+                    options.notify = Some(
+                        Notify::parse(when)
+                            .ok_or_else(|| invalid(name, when, "never, always, on_error or if_longer_than:30s"))?,
+                    )
+                }
+                None => options.notify = Some(Notify::Always),
+            },
+            "-o" | "--output" => options.output_file = Some(PathBuf::from(value(args, &mut i, name, inline)?)),
+            "-b" | "--backup" => options.backup_dir = Some(PathBuf::from(value(args, &mut i, name, inline)?)),
+            "--watch" => options.watch.push(value(args, &mut i, name, inline)?),
+            "--title" => options.title = Some(value(args, &mut i, name, inline)?),
+            "--terminal" => options.terminal = Some(value(args, &mut i, name, inline)?),
+            "--cwd" => options.cwd = Some(environment::expand_home(&value(args, &mut i, name, inline)?)),
+            "--shell" => {
+                let v = value(args, &mut i, name, inline)?;
+                options.shell =
+                    Some(shell::Shell::parse(&v).ok_or_else(|| invalid(name, &v, "sh, bash, zsh, fish, pwsh, nu or cmd"))?);
+            }
+            "--geometry" => {
+                let v = value(args, &mut i, name, inline)?;
+                let geometry = GeometryOverride::parse_cells(&v)
+                    .ok_or_else(|| invalid(name, &v, "COLSxROWS[+X+Y], e.g. 120x40+100+50"))?;
+                options.geometry = GeometryOverride {
+                    x: geometry.x.or(options.geometry.x),
+                    y: geometry.y.or(options.geometry.y),
+                    ..geometry
+                };
+            }
+            "--size" => {
+                let v = value(args, &mut i, name, inline)?;
+                options
+                    .geometry
+                    .parse_size(&v)
+                    .ok_or_else(|| invalid(name, &v, "WIDTHxHEIGHT in pixels, e.g. 1000x700"))?;
+            }
+            "--pos" => {
+                let v = value(args, &mut i, name, inline)?;
+                options
+                    .geometry
+                    .parse_pos(&v)
+                    .ok_or_else(|| invalid(name, &v, "X,Y in pixels, e.g. 50,50"))?;
+            }
+            _ => return Err(format!("Unknown option '{}' (run rund --help for usage)", arg)),
+        }
+        i += 1;
+    }
+
+    options.args = args[..i].to_vec();
+    Ok(Invocation::Run {
+        options: Box::new(options),
+        app: args.get(i).cloned(),
+        args: args.get(i + 1..).unwrap_or_default().to_vec(),
+    })
+}
+
+pub fn print_help() {
+    #[cfg(target_os = "windows")]
+    let terminal_info = r#"
+TERMINAL SELECTION (Windows only):
+    terminal = "cmd" or terminal = "wt"
+"#;
+
+    #[cfg(not(target_os = "windows"))]
+    let terminal_info = "";
+
+    println!(
+        r#"rund - Run CLI apps in detached terminal popup
+
+USAGE:
+    rund [-v] [OPTIONS] [--] [APP] [ARGS...]
+    rund run [OPTIONS] [--] [APP] [ARGS...]
+    rund qf [OPTIONS] [--first] [APP] < OUTPUT
+    rund config
+    rund backup [FILE...]
+    rund monitors
+    rund calibrate [BACKEND]
+    rund history [--grep PATTERN] [-n COUNT | --all]
+    rund again [N]
+    rund ps
+    rund kill|focus SESSION
+    rund layout up FILE | layout down [FILE]
+    rund daemon
+    rund help
+
+Options go before the app; everything after the app is passed to it.
+
+COMMANDS:
+    run                 Run an app (the default)
+    qf                  Open a location from compiler/grep output on stdin
+    config              Show config file path
+    backup [FILE...]    Back up files now, or list the backup directory
+    monitors            List monitors usable with monitor = <index|name>
+    calibrate [NAME]    Measure this terminal's cell size for pixel conversion
+    history             List past launches, newest first (--grep to filter)
+    again [N]           Run launch N from the history again (default: the last)
+    ps                  List running popups
+    kill SESSION        End a popup (its number in rund ps, id, title or file)
+    focus SESSION       Raise a popup's window
+    layout up FILE      Start the popups a layout file describes
+    layout down [FILE]  Close the popups a layout (or any layout) started
+    daemon              Serve launches over a local socket, reloading the
+                        config when it changes (Unix)
+    help                Show this help
+
+OPTIONS:
+    -t, --top           Always-on-top (macOS/Linux only)
+    -c, --clipboard     Read clipboard to file
+    -o, --output FILE   Specify output file path
+    -b, --backup DIR    Override backup directory
+    --geometry GEOM     Window size in cells and position, COLSxROWS[+X+Y]
+    --size WxH          Window size in pixels
+    --pos X,Y           Window position in pixels
+    --pause             Always pause after the command
+    --no-pause          Never pause after the command
+    --pause MODE        Pause mode (or --pause=MODE): never, always, auto,
+                        on_error, on_error_or_fast
+    --notify[=WHEN]     Desktop notification when the command finishes:
+                        always (default), on_error, if_longer_than:30s, never
+    --watch GLOB        Re-run the command in the popup when matching files
+                        change (repeatable), e.g. --watch 'src/**/*.rs'
+    --title TITLE       Window title
+    --terminal NAME     Terminal to use for this launch (tmux: a tmux window)
+    --shell NAME        Shell for the command: bash, zsh, fish, sh, pwsh, nu, cmd
+    --cwd DIR           Working directory for the command
+    --from-quickfix     Same as rund qf
+    --first             Open the first quickfix location without asking
+    -v, --verbose       Show what the launch does on stderr (-vv for more);
+                        also before a command, e.g. rund -v layout up dev
+    -h, --help          Show this help
+    --                  End of rund options, e.g. for an app named like a command
+
+EXAMPLES:
+    rund nvim file.txt
+    rund -c -o c:\temp\test.py bat
+    rund "python -m rich.emoji"
+    rund --geometry 120x40+100+50 --pause=on_error cargo build
+    rund bat -n -c file.txt
+    rund -- config
+    cargo build 2>&1 | rund qf nvim
+    rund --watch 'src/**/*.rs' cargo check
+{}
+CONFIG: {}
+
+    width = 800
+    height = 600
+    auto_position = false
+    x = 100
+    y = 100
+    terminal = "cmd"
+    pause_behavior = "auto"
+    
+    # Customize app behavior:
+    editor_apps = "vim, nvim, nano, emacs, micro, helix, hx, code, subl"
+    viewer_apps = "bat, less, more, cat, type"
+    always_pause_apps = "python, python3, node, ruby, perl, php"
+    
+    backup_dir = "backups"
+"#,
+        terminal_info,
+        Config::path().display()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &[&str]) -> Vec<String> {
+        line.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    // The parts of an invocation the cases look at; options only when set
+    fn summary(invocation: &Invocation) -> String {
+        let (options, app, app_args) = match invocation {
+            Invocation::Run { options, app, args } => (options, app, args),
+            other => return format!("{:?}", other),
+        };
+        let mut parts = vec![format!("run {:?} {:?}", app, app_args)];
+        if let Some(pause) = options.pause {
+            parts.push(format!("pause={:?}", pause));
+        }
+        if let Some(ref title) = options.title {
+            parts.push(format!("title={}", title));
+        }
+        if let Some(ref terminal) = options.terminal {
+            parts.push(format!("terminal={}", terminal));
+        }
+        if let Some(ref output) = options.output_file {
+            parts.push(format!("output={}", output.display()));
+        }
+        if !options.watch.is_empty() {
+            parts.push(format!("watch={}", options.watch.join(",")));
+        }
+        if options.always_on_top {
+            parts.push("top".to_string());
+        }
+        if options.from_quickfix {
+            parts.push("quickfix".to_string());
+        }
+        if options.first {
+            parts.push("first".to_string());
+        }
+        if options.verbose > 0 {
+            parts.push(format!("verbose={}", options.verbose));
+        }
+        parts.join(" ")
+    }
+
+    #[test]
+    fn parses() {
+        let cases: &[(&[&str], &str)] = &[
+            (&[], "run None []"),
+            (&["nvim", "notes.md"], r#"run Some("nvim") ["notes.md"]"#),
+            // Options before the app are rund's, after it the app's
+            (&["-t", "--title", "t", "bat", "-n", "--title", "x"], r#"run Some("bat") ["-n", "--title", "x"] title=t top"#),
+            (&["--title=a b", "--terminal", "kitty", "htop"], r#"run Some("htop") [] title=a b terminal=kitty"#),
+            (&["-o", "out.txt", "--watch", "src/**", "--watch=*.toml", "cat"], r#"run Some("cat") [] output=out.txt watch=src/**,*.toml"#),
+            // `--` ends rund's options, also for apps named like a subcommand or option
+            (&["--", "config"], r#"run Some("config") []"#),
+            (&["--top", "--", "-weird", "--top"], r#"run Some("-weird") ["--top"] top"#),
+            (&["-", "x"], r#"run Some("-") ["x"]"#),
+            (&["run", "ps", "aux"], r#"run Some("ps") ["aux"]"#),
+            (&["python", "-c", "print(1)"], r#"run Some("python") ["-c", "print(1)"]"#),
+            // A bare --pause is always; a mode may follow it or be inline
+            (&["--pause", "cargo", "test"], r#"run Some("cargo") ["test"] pause=Always"#),
+            (&["--pause", "on_error", "cargo"], r#"run Some("cargo") [] pause=OnError"#),
+            (&["--pause=never", "cargo"], r#"run Some("cargo") [] pause=Never"#),
+            (&["--no-pause", "cargo"], r#"run Some("cargo") [] pause=Never"#),
+            (&["--pause", "--", "auto"], r#"run Some("auto") [] pause=Always"#),
+            (&["-v", "--verbose", "-vv", "make"], r#"run Some("make") [] verbose=4"#),
+            (&["qf", "--first", "nvim"], r#"run Some("nvim") [] quickfix first"#),
+            (&["--from-quickfix", "nvim"], r#"run Some("nvim") [] quickfix"#),
+            (&["-h", "nvim"], "Help"),
+            (&["help"], "Help"),
+            (&["config"], "ConfigPath"),
+            (&["--config"], "ConfigPath"),
+            (&["monitors"], "Monitors"),
+            (&["calibrate"], "Calibrate(None)"),
+            (&["calibrate", "kitty"], r#"Calibrate(Some("kitty"))"#),
+            (&["backup"], "Backup([])"),
+            (&["backup", "a.txt"], r#"Backup(["a.txt"])"#),
+            (&["history"], "History { grep: None, limit: 20 }"),
+            (&["history", "--grep", "cargo", "-n", "5"], r#"History { grep: Some("cargo"), limit: 5 }"#),
+            (&["again"], "Again(1)"),
+            (&["again", "3"], "Again(3)"),
+            (&["daemon"], "Daemon"),
+            (&["ps"], "Ps"),
+            (&["kill", "2"], r#"Kill("2")"#),
+            (&["focus", "notes.md"], r#"Focus("notes.md")"#),
+            (&["layout", "up", "dev"], r#"LayoutUp("dev")"#),
+            (&["layout", "down"], "LayoutDown(None)"),
+            (&["layout", "down", "dev"], r#"LayoutDown(Some("dev"))"#),
+            (&["--record-geometry", "nvim"], r#"RecordGeometry("nvim")"#),
+            (&["--record-exit", "id", "3"], r#"RecordExit { id: "id", code: Some(3) }"#),
+            (&["--record-exit", "id", ""], r#"RecordExit { id: "id", code: None }"#),
+            (&["--watch-loop", "*.rs", "--", "cargo", "check"], r#"WatchLoop { globs: ["*.rs"], argv: ["cargo", "check"] }"#),
+        ];
+        for (line, expected) in cases {
+            match parse(&args(line)) {
+                Ok(invocation) => assert_eq!(summary(&invocation), *expected, "rund {}", line.join(" ")),
+                Err(e) => panic!("rund {}: {}", line.join(" "), e),
+            }
+        }
+        assert!(matches!(parse(&args(&["history", "--all"])), Ok(Invocation::History { limit: usize::MAX, .. })));
+    }
+
+    #[test]
+    fn errors_name_the_argument() {
+        let cases: &[(&[&str], &str)] = &[
+            (&["--bogus", "nvim"], "Unknown option '--bogus'"),
+            (&["--title"], "--title requires a value"),
+            (&["--top=yes", "nvim"], "--top does not take a value"),
+            (&["--pause=sometimes", "nvim"], "Invalid value 'sometimes' for --pause"),
+            (&["--notify=loudly", "nvim"], "Invalid value 'loudly' for --notify"),
+            (&["--geometry", "big", "nvim"], "Invalid value 'big' for --geometry"),
+            (&["--size", "10", "nvim"], "Invalid value '10' for --size"),
+            (&["--pos", "a,b", "nvim"], "Invalid value 'a,b' for --pos"),
+            (&["--shell", "tcsh", "nvim"], "Invalid value 'tcsh' for --shell"),
+            (&["config", "extra"], "Unexpected argument 'extra'"),
+            (&["calibrate", "kitty", "extra"], "Unexpected argument 'extra'"),
+            (&["kill"], "kill requires a session"),
+            (&["focus", "1", "2"], "Unexpected argument '2'"),
+            (&["again", "0"], "Invalid value '0' for again"),
+            (&["history", "-n", "many"], "Invalid value 'many' for -n"),
+            (&["history", "--since"], "Unexpected argument '--since'"),
+            (&["layout", "up"], "layout up requires a layout file"),
+            (&["layout", "sideways"], "layout requires up FILE or down [FILE]"),
+            (&["--record-exit", "id"], "--record-exit requires a launch id and an exit code"),
+            (&["--watch-loop", "*.rs", "cargo"], "--watch-loop requires globs"),
+        ];
+        for (line, expected) in cases {
+            match parse(&args(line)) {
+                Ok(invocation) => panic!("rund {}: parsed as {}", line.join(" "), summary(&invocation)),
+                Err(e) => assert!(e.contains(expected), "rund {}: {:?} lacks {:?}", line.join(" "), e, expected),
+            }
+        }
+    }
+
+    #[test]
+    fn verbosity_before_the_command() {
+        let line = args(&["-v", "-vv", "layout", "up", "dev"]);
+        let (verbosity, rest) = verbosity(&line);
+        assert_eq!(verbosity, 3);
+        assert_eq!(rest, &line[2..]);
+        assert_eq!(verbosity_of(&["--verbose"]), 1);
+        assert_eq!(verbosity_of(&["nvim", "-v"]), 0);
+    }
+
+    fn verbosity_of(line: &[&str]) -> u8 {
+        verbosity(&args(line)).0
+    }
+}
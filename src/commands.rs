@@ -0,0 +1,455 @@
+// src/commands.rs - rund's subcommands, and the command line that picks one
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+use std::path::PathBuf;
+#[cfg(not(target_os = "windows"))]
+use std::process::Command;
+
+use crate::cli::{self, Invocation};
+use crate::config::{cell_sizes_path, history_path, save_window_geometry};
+use crate::hooks::create_backup;
+use crate::launch::{run_in_terminal, GeometryOverride, RunOptions};
+use crate::{cells, history, hooks, layout, log, paths, quickfix, runtime, screen, watch, Config, LaunchRequest, Launcher};
+
+// Read compiler/grep output from stdin and let the user choose a location;
+// None when the picker was cancelled
+fn quickfix_location(first: bool) -> io::Result<Option<String>> {
+    let mut input = String::new();
+    io::stdin().read_to_string(&mut input)?;
+
+    let entries = quickfix::parse(&input, &env::current_dir()?);
+    let index = match entries.len() {
+        0 => {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "no file locations found in the input",
+            ))
+        }
+        1 => Some(0),
+        _ if first => Some(0),
+        _ => quickfix::pick(&entries)?,
+    };
+    Ok(index.map(|i| entries[i].location()))
+}
+
+// `rund backup FILE...` copies files into the backup directory right away;
+// without files it lists what is there, oldest first
+pub fn run_backup(files: &[PathBuf], config: &Config) -> io::Result<()> {
+    if !files.is_empty() {
+        for file in files {
+            if !file.is_file() {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("{} is not a file", file.display()),
+                ));
+            }
+            println!("Backup created: {}", create_backup(file, &config.backup_dir)?.display());
+        }
+        return Ok(());
+    }
+
+    let mut backups: Vec<(std::time::SystemTime, PathBuf)> = match fs::read_dir(&config.backup_dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    backups.sort();
+    if backups.is_empty() {
+        println!("No backups in {}", config.backup_dir.display());
+    }
+    for (_, path) in backups {
+        println!("{}", path.display());
+    }
+    Ok(())
+}
+
+// `rund history`: newest first, numbered the way `rund again` takes them
+pub fn run_history(grep: Option<&str>, limit: usize) {
+    let entries = history::load(&history_path());
+    if entries.is_empty() {
+        println!("No launches recorded in {}", history_path().display());
+        return;
+    }
+
+    let now = runtime::now_secs();
+    let grep = grep.map(str::to_lowercase);
+    let matching = entries.iter().rev().enumerate().filter(|(_, entry)| match grep {
+        Some(ref pattern) => {
+            entry.command.to_lowercase().contains(pattern) || entry.cwd.to_lowercase().contains(pattern)
+        }
+        None => true,
+    });
+    for (i, entry) in matching.take(limit) {
+        let status = match (entry.exit_code, entry.ended) {
+            (Some(code), _) => format!("exit {}", code),
+            (None, Some(_)) => "exited".to_string(),
+            (None, None) => "-".to_string(),
+        };
+        let mut command: Vec<String> = entry
+            .options
+            .iter()
+            .map(|arg| if arg.contains(char::is_whitespace) { format!("\"{}\"", arg) } else { arg.clone() })
+            .collect();
+        command.push(entry.command.clone());
+        println!(
+            "{:>4}  {:>8}  {:<8}  {}  ({})",
+            i + 1,
+            history::age(now.saturating_sub(entry.started)),
+            status,
+            command.join(" "),
+            entry.cwd
+        );
+        for backup in &entry.backups {
+            println!("{:26}backup: {}", "", backup);
+        }
+    }
+}
+
+// `rund ps`: running sessions, oldest first, numbered for kill and focus
+pub fn run_ps() {
+    let sessions = runtime::sessions();
+    if sessions.is_empty() {
+        println!("No running sessions");
+        return;
+    }
+
+    let now = runtime::now_secs();
+    println!("{:>3}  {:>7}  {:<14}  {:<10}  {:>8}  TITLE", "#", "PID", "BACKEND", "APP", "STARTED");
+    for (i, session) in sessions.iter().enumerate() {
+        let pid = session.pid.map(|pid| pid.to_string()).unwrap_or_else(|| "-".to_string());
+        println!(
+            "{:>3}  {:>7}  {:<14}  {:<10}  {:>8}  {}",
+            i + 1,
+            pid,
+            session.backend,
+            session.app,
+            history::age(now.saturating_sub(session.created)),
+            session.title
+        );
+        for file in &session.files {
+            println!("{:52}{}", "", file.display());
+        }
+    }
+}
+
+// The running session `target` names, as rund ps numbers them or by id,
+// title or file
+fn with_session(target: &str, action: fn(&runtime::Session) -> io::Result<()>) -> io::Result<()> {
+    let sessions = runtime::sessions();
+    match runtime::find_session(&sessions, target) {
+        Some(session) => action(session),
+        None => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("No running session matches '{}' (see rund ps)", target),
+        )),
+    }
+}
+
+// `rund again N`: the same command and options, in the same directory and
+// with the window geometry the launch ended up with
+pub fn run_again(n: usize, config: &Config) -> io::Result<()> {
+    let entries = history::load(&history_path());
+    let entry = entries.iter().rev().nth(n - 1).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, format!("No launch #{} in the history", n))
+    })?;
+
+    let mut options = match cli::parse(&entry.options) {
+        Ok(cli::Invocation::Run { options, .. }) => *options,
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Launch #{} has options this rund does not understand", n),
+            ))
+        }
+    };
+    // Relative -o paths were relative to this directory too
+    let cwd = PathBuf::from(&entry.cwd);
+    if !cwd.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Working directory {} no longer exists", entry.cwd),
+        ));
+    }
+    options.client_dir = Some(cwd.clone());
+    options.cwd = Some(cwd);
+    // The picked quickfix location is already part of the command
+    options.from_quickfix = false;
+    options.first = false;
+    if let Some(g) = entry.geometry {
+        options.geometry = GeometryOverride {
+            width: Some(g.width),
+            height: Some(g.height),
+            cols: None,
+            rows: None,
+            x: g.x,
+            y: g.y,
+        };
+    }
+
+    println!("{}", entry.command);
+    let targets: Vec<paths::Target> = entry
+        .files
+        .iter()
+        .map(|file| paths::Target { path: PathBuf::from(file), line: None, col: None })
+        .collect();
+    run_in_terminal(&entry.command, &targets, config, &options).map(|_| ())
+}
+
+// `rund kill` and `rund focus`
+pub fn kill(target: &str) -> io::Result<()> {
+    with_session(target, runtime::kill)
+}
+
+pub fn focus(target: &str) -> io::Result<()> {
+    with_session(target, runtime::focus)
+}
+
+// `rund monitors`
+pub fn monitors() {
+    for (index, monitor) in screen::monitors().iter().enumerate() {
+        println!(
+            "{}: {} {}x{}+{}+{}{}",
+            index,
+            monitor.name,
+            monitor.rect.width,
+            monitor.rect.height,
+            monitor.rect.x,
+            monitor.rect.y,
+            if monitor.primary { " (primary)" } else { "" }
+        );
+    }
+}
+
+// `rund calibrate`: run inside the terminal to measure; the backend name is
+// guessed from its environment unless given
+pub fn calibrate(backend: Option<String>) -> io::Result<()> {
+    let backend = backend.or_else(cells::current_backend).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "Could not tell which terminal this is; run: rund calibrate <backend>",
+        )
+    })?;
+    let cell = cells::measure_current()?;
+    cells::save_calibrated(&cell_sizes_path(), &backend, cell)?;
+    println!("{}: {}x{} pixels per cell", backend, cell.width, cell.height);
+    println!("Saved to {}", cell_sizes_path().display());
+    Ok(())
+}
+
+// `--record-geometry APP`, from the wrapper of a remember_geometry popup
+pub fn record_geometry(app: &str) -> io::Result<()> {
+    match screen::current_window() {
+        Some(rect) => save_window_geometry(app, rect),
+        None => Ok(()),
+    }
+}
+
+// `--record-exit ID CODE`: hooks and backups are left for the exit when the
+// launch has any
+pub fn record_exit(id: &str, code: Option<i64>) -> io::Result<()> {
+    match hooks::Completion::take(id) {
+        Some(completion) => completion.finish(code),
+        None => history::record_exit(&history_path(), id, runtime::now_secs(), code),
+    }
+}
+
+pub fn layout_up(name: &str, config: &Config) -> io::Result<()> {
+    layout::up(&layout::resolve(name), config)
+}
+
+pub fn layout_down(name: Option<&str>) -> io::Result<()> {
+    layout::down(name.map(layout::resolve).as_deref())
+}
+
+pub fn watch_loop(globs: &[String], argv: &[String]) -> io::Result<()> {
+    watch::run(globs, argv)
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn daemon() -> io::Result<()> {
+    crate::daemon::serve()
+}
+
+#[cfg(target_os = "windows")]
+pub fn daemon() -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "rund daemon needs Unix domain sockets"))
+}
+
+// -v given before the app adds to any among the options; logging to stderr
+// starts here, to the log file once the config is read
+pub fn init_logging(verbosity: u8, options: &mut RunOptions) {
+    options.verbose = options.verbose.saturating_add(verbosity);
+    log::init(options.verbose);
+}
+
+// A running daemon launches faster, with its config already loaded; stdin
+// for quickfix can only be read here, and -v shows what happens only here.
+// None when there is no daemon.
+#[cfg(not(target_os = "windows"))]
+pub fn forward(args: &[String], options: &RunOptions) -> Option<io::Result<()>> {
+    if options.from_quickfix || options.verbose > 0 {
+        return None;
+    }
+    crate::daemon::forward(args)
+}
+
+#[cfg(target_os = "windows")]
+pub fn forward(_args: &[String], _options: &RunOptions) -> Option<io::Result<()>> {
+    None
+}
+
+// `rund [OPTIONS] APP ARGS...` without a daemon, through the Launcher
+pub fn run(config: Config, options: RunOptions, app: Option<String>, mut args: Vec<String>) -> io::Result<()> {
+    let app = app.or_else(|| config.default_app.clone()).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "No app specified and no default_app in config")
+    })?;
+
+    if options.from_quickfix {
+        match quickfix_location(options.first) {
+            Ok(Some(location)) => args.push(location),
+            Ok(None) => return Ok(()),
+            Err(e) => return Err(io::Error::new(e.kind(), format!("Quickfix: {}", e))),
+        }
+    } else if options.first {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "--first only applies to rund qf and --from-quickfix",
+        ));
+    }
+
+    Launcher::new(config)
+        .launch(&LaunchRequest::from_options(app, args, options))
+        .map(|_| ())
+        .map_err(|e| io::Error::new(e.kind(), format!("Failed to run terminal: {}", e)))
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn show_error(msg: &str) {
+    log::shown_error(msg);
+    #[cfg(target_os = "macos")]
+    {
+        Command::new("osascript")
+            .args([
+                "-e",
+                &format!(
+                    r#"display dialog "{}" with title "rund - Error" buttons {{"OK"}} default button "OK" with icon stop"#,
+                    msg.replace('"', "\\\"")
+                ),
+            ])
+            .output()
+            .ok();
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        Command::new("zenity")
+            .args([
+                "--error",
+                "--title=rund - Error",
+                &format!("--text={}", msg),
+                "--width=400",
+            ])
+            .output()
+            .or_else(|_| {
+                Command::new("kdialog")
+                    .args(["--error", msg, "--title", "rund - Error"])
+                    .output()
+            })
+            .or_else(|_| {
+                Command::new("notify-send")
+                    .args(["-u", "critical", "rund - Error", msg])
+                    .output()
+            })
+            .ok();
+    }
+
+    eprintln!("Error: {}", msg);
+}
+
+#[cfg(target_os = "windows")]
+pub fn show_error(msg: &str) {
+    log::shown_error(msg);
+    crate::terminal::windows::show_error_centered(msg);
+    eprintln!("Error: {}", msg);
+}
+
+fn load_config_or_exit() -> Config {
+    log::hold_for_config();
+    match Config::load() {
+        Ok(c) => c,
+        Err(e) => {
+            show_error(&format!("Failed to load config: {}", e));
+            std::process::exit(1);
+        }
+    }
+}
+
+// Show the error the way every failing subcommand does, and exit
+fn exit_on_error<E: std::fmt::Display>(result: Result<(), E>, what: &str) {
+    if let Err(e) = result {
+        show_error(&format!("{}: {}", what, e));
+        std::process::exit(1);
+    }
+}
+
+// Everything but running an app
+fn subcommand(invocation: Invocation) {
+    match invocation {
+        Invocation::Run { .. } => unreachable!("launches are handled in main"),
+        Invocation::Help => cli::print_help(),
+        Invocation::ConfigPath => println!("Config file: {}", Config::path().display()),
+        Invocation::Calibrate(backend) => exit_on_error(calibrate(backend), "Calibration failed"),
+        Invocation::RecordGeometry(app) => {
+            exit_on_error(record_geometry(&app), "Could not save window geometry")
+        }
+        Invocation::RecordExit { id, code } => exit_on_error(record_exit(&id, code), "Could not record the exit"),
+        Invocation::LayoutUp(name) => exit_on_error(layout_up(&name, &load_config_or_exit()), "Layout failed"),
+        Invocation::LayoutDown(name) => exit_on_error(layout_down(name.as_deref()), "Layout failed"),
+        Invocation::WatchLoop { globs, argv } => exit_on_error(watch_loop(&globs, &argv), "Watch failed"),
+        Invocation::History { grep, limit } => run_history(grep.as_deref(), limit),
+        Invocation::Again(n) => exit_on_error(run_again(n, &load_config_or_exit()), "Failed to run again"),
+        Invocation::Daemon => exit_on_error(daemon(), "Daemon failed"),
+        Invocation::Ps => run_ps(),
+        Invocation::Kill(target) => exit_on_error(kill(&target), "Failed to kill"),
+        Invocation::Focus(target) => exit_on_error(focus(&target), "Failed to focus"),
+        Invocation::Monitors => monitors(),
+        Invocation::Backup(files) => exit_on_error(run_backup(&files, &load_config_or_exit()), "Backup failed"),
+    }
+}
+
+// The rund command line; see cli_main
+pub fn main(args: Vec<String>) {
+    let (verbosity, args) = cli::verbosity(&args);
+    let invocation = match cli::parse(args) {
+        Ok(invocation) => invocation,
+        Err(e) => {
+            show_error(&e);
+            std::process::exit(1);
+        }
+    };
+
+    let (mut options, app, app_args) = match invocation {
+        Invocation::Run { options, app, args } => (*options, app, args),
+        other => {
+            log::init(verbosity);
+            return subcommand(other);
+        }
+    };
+    init_logging(verbosity, &mut options);
+
+    if let Some(result) = forward(args, &options) {
+        if let Err(e) = result {
+            show_error(&e.to_string());
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Err(e) = run(load_config_or_exit(), options, app, app_args) {
+        show_error(&e.to_string());
+        std::process::exit(1);
+    }
+}
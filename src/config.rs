@@ -0,0 +1,1339 @@
+// src/config.rs - config.toml: the [terminal] defaults and per-app sections,
+// where the file lives, and the state rund keeps next to it
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::hooks::create_backup;
+use crate::launch::RunOptions;
+use crate::placement::{self, Placement};
+use crate::terminal::backend_name;
+use crate::{cells, environment, hooks, log, notify, paths, screen, shell};
+
+pub(crate) fn parse_bool(value: &str) -> bool {
+    matches!(value.to_lowercase().as_str(), "true" | "1" | "yes")
+}
+
+// A geometry value from config: plain pixels or "60%" of the screen
+pub(crate) fn parse_length(value: &str) -> Option<Result<i32, f32>> {
+    match value.strip_suffix('%') {
+        Some(pct) => pct.trim().parse::<f32>().ok().filter(|p| *p >= 0.0).map(Err),
+        None => value.parse().ok().map(Ok),
+    }
+}
+
+/// A popup's size and position, in pixels unless `cols`/`rows` are set
+#[derive(Debug, Clone)]
+pub struct AppGeometry {
+    pub width: u32,
+    pub height: u32,
+    pub x: i32,
+    pub y: i32,
+    /// Leave the position to the window manager
+    pub auto_position: bool,
+    /// Size in character cells; wins over width/height when set
+    pub cols: Option<u32>,
+    pub rows: Option<u32>,
+    // Screen-relative parts, turned into the pixel fields above by `resolve`
+    pub(crate) relative: RelativeGeometry,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct RelativeGeometry {
+    pub(crate) width_pct: Option<f32>,
+    pub(crate) height_pct: Option<f32>,
+    pub(crate) x_pct: Option<f32>,
+    pub(crate) y_pct: Option<f32>,
+    pub(crate) anchor: Option<Anchor>,
+    pub(crate) margin_x: i32,
+    pub(crate) margin_y: i32,
+    // Monitor the position is relative to (primary screen if unset)
+    pub(crate) monitor: Option<screen::MonitorTarget>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    pub(crate) fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().replace('_', "-").as_str() {
+            "top-left" => Some(Anchor::TopLeft),
+            "top" => Some(Anchor::Top),
+            "top-right" => Some(Anchor::TopRight),
+            "left" => Some(Anchor::Left),
+            "center" | "centre" => Some(Anchor::Center),
+            "right" => Some(Anchor::Right),
+            "bottom-left" => Some(Anchor::BottomLeft),
+            "bottom" => Some(Anchor::Bottom),
+            "bottom-right" => Some(Anchor::BottomRight),
+            _ => None,
+        }
+    }
+
+    // Horizontal and vertical alignment: 0 = start, 1 = center, 2 = end
+    pub(crate) fn alignment(self) -> (i32, i32) {
+        match self {
+            Anchor::TopLeft => (0, 0),
+            Anchor::Top => (1, 0),
+            Anchor::TopRight => (2, 0),
+            Anchor::Left => (0, 1),
+            Anchor::Center => (1, 1),
+            Anchor::Right => (2, 1),
+            Anchor::BottomLeft => (0, 2),
+            Anchor::Bottom => (1, 2),
+            Anchor::BottomRight => (2, 2),
+        }
+    }
+}
+
+impl Default for AppGeometry {
+    fn default() -> Self {
+        AppGeometry {
+            width: 800,
+            height: 600,
+            x: 100,
+            y: 100,
+            auto_position: false,
+            cols: None,
+            rows: None,
+            relative: RelativeGeometry::default(),
+        }
+    }
+}
+
+impl AppGeometry {
+    // Apply one geometry key from [terminal] or an [app] section. Returns
+    // false for keys that are not about geometry.
+    pub(crate) fn set(&mut self, key: &str, value: &str) -> bool {
+        match key {
+            "width" => match parse_length(value) {
+                Some(Ok(v)) if v >= 0 => {
+                    self.width = v as u32;
+                    self.cols = None;
+                    self.relative.width_pct = None;
+                }
+                Some(Err(pct)) => {
+                    self.cols = None;
+                    self.relative.width_pct = Some(pct);
+                }
+                _ => {}
+            },
+            "height" => match parse_length(value) {
+                Some(Ok(v)) if v >= 0 => {
+                    self.height = v as u32;
+                    self.rows = None;
+                    self.relative.height_pct = None;
+                }
+                Some(Err(pct)) => {
+                    self.rows = None;
+                    self.relative.height_pct = Some(pct);
+                }
+                _ => {}
+            },
+            "cols" => {
+                if let Ok(v) = value.parse::<u32>() {
+                    self.cols = Some(v.max(1));
+                    self.relative.width_pct = None;
+                }
+            }
+            "rows" => {
+                if let Ok(v) = value.parse::<u32>() {
+                    self.rows = Some(v.max(1));
+                    self.relative.height_pct = None;
+                }
+            }
+            "x" => match parse_length(value) {
+                Some(Ok(v)) => {
+                    self.x = v;
+                    self.relative.x_pct = None;
+                }
+                Some(Err(pct)) => self.relative.x_pct = Some(pct),
+                None => {}
+            },
+            "y" => match parse_length(value) {
+                Some(Ok(v)) => {
+                    self.y = v;
+                    self.relative.y_pct = None;
+                }
+                Some(Err(pct)) => self.relative.y_pct = Some(pct),
+                None => {}
+            },
+            "auto_position" => {
+                self.auto_position = parse_bool(value);
+            }
+            "anchor" => {
+                self.relative.anchor = Anchor::parse(value);
+            }
+            "margin" => {
+                if let Ok(v) = value.parse() {
+                    self.relative.margin_x = v;
+                    self.relative.margin_y = v;
+                }
+            }
+            "monitor" => {
+                self.relative.monitor = screen::MonitorTarget::parse(value);
+            }
+            "margin_x" => {
+                if let Ok(v) = value.parse() {
+                    self.relative.margin_x = v;
+                }
+            }
+            "margin_y" => {
+                if let Ok(v) = value.parse() {
+                    self.relative.margin_y = v;
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    pub(crate) fn is_relative(&self) -> bool {
+        self.relative != RelativeGeometry::default()
+    }
+
+    // A window rect recorded by remember_geometry replaces the configured
+    // size and position, relative parts included
+    pub(crate) fn remembered(self, rect: screen::Rect) -> AppGeometry {
+        AppGeometry {
+            width: rect.width,
+            height: rect.height,
+            x: rect.x,
+            y: rect.y,
+            auto_position: false,
+            cols: None,
+            rows: None,
+            relative: RelativeGeometry::default(),
+        }
+    }
+
+    // Columns and rows for a terminal with the given cell size
+    pub(crate) fn cells(&self, cell: cells::CellSize) -> (u32, u32) {
+        (
+            self.cols.unwrap_or(self.width / cell.width.max(1)).max(1),
+            self.rows.unwrap_or(self.height / cell.height.max(1)).max(1),
+        )
+    }
+
+    // Width and height in pixels for a terminal with the given cell size
+    pub(crate) fn pixels(&self, cell: cells::CellSize) -> (u32, u32) {
+        (
+            self.cols.map_or(self.width, |cols| cols * cell.width),
+            self.rows.map_or(self.height, |rows| rows * cell.height),
+        )
+    }
+
+    // Turn percentages and anchors into pixels on the given screen. Without a
+    // known screen the relative parts are dropped and the pixel values stay.
+    // Plain x/y are offsets within the screen once a monitor was chosen.
+    pub(crate) fn resolve(mut self, screen: Option<screen::Rect>, cell: cells::CellSize) -> AppGeometry {
+        let relative = std::mem::take(&mut self.relative);
+        let screen = match screen {
+            Some(screen) => screen,
+            None => return self,
+        };
+        let of = |total: u32, pct: f32| (total as f32 * pct / 100.0).round() as i32;
+
+        if relative.monitor.is_some() && !self.auto_position {
+            self.x += screen.x;
+            self.y += screen.y;
+        }
+
+        if let Some(pct) = relative.width_pct {
+            self.width = of(screen.width, pct) as u32;
+        }
+        if let Some(pct) = relative.height_pct {
+            self.height = of(screen.height, pct) as u32;
+        }
+        if let Some(pct) = relative.x_pct {
+            self.x = screen.x + of(screen.width, pct);
+            self.auto_position = false;
+        }
+        if let Some(pct) = relative.y_pct {
+            self.y = screen.y + of(screen.height, pct);
+            self.auto_position = false;
+        }
+
+        if let Some(anchor) = relative.anchor {
+            let place = |align: i32, start: i32, total: u32, size: u32, margin: i32| match align {
+                0 => start + margin,
+                1 => start + (total as i32 - size as i32) / 2,
+                _ => start + total as i32 - size as i32 - margin,
+            };
+            let (horizontal, vertical) = anchor.alignment();
+            let (width, height) = self.pixels(cell);
+            self.x = place(horizontal, screen.x, screen.width, width, relative.margin_x);
+            self.y = place(vertical, screen.y, screen.height, height, relative.margin_y);
+            self.auto_position = false;
+        }
+
+        self
+    }
+}
+
+// Per-app overrides that are not geometry, from the same [app] sections
+#[derive(Debug, Clone, Default)]
+pub(crate) struct AppSettings {
+    pub(crate) pause_timeout: Option<u64>,
+    pub(crate) remember_geometry: Option<bool>,
+    pub(crate) single_instance: Option<bool>,
+    pub(crate) notify: Option<notify::Notify>,
+    pub(crate) shell: Option<shell::Shell>,
+    pub(crate) shell_login: Option<bool>,
+    pub(crate) shell_interactive: Option<bool>,
+    pub(crate) environment: environment::Environment,
+    pub(crate) hooks: hooks::Hooks,
+    // How the app takes a file position, e.g. "+{line} {file}"
+    pub(crate) goto_line: Option<String>,
+}
+
+/// rund's config.toml: the defaults from its terminal section and per-app sections
+#[derive(Debug)]
+pub struct Config {
+    // Default geometry from [terminal], also the base for per-app sections
+    pub(crate) geometry: AppGeometry,
+    pub(crate) default_app: Option<String>,
+    pub(crate) backup_dir: PathBuf,
+    pub(crate) pause_behavior: PauseBehavior,
+    // Seconds under which `on_error_or_fast` treats an exit as suspicious
+    pub(crate) fast_exit_secs: u64,
+    // Auto-close countdown for the pause prompt (None = wait for a key forever)
+    pub(crate) pause_timeout: Option<u64>,
+    pub(crate) editor_apps: Vec<String>,
+    pub(crate) viewer_apps: Vec<String>,
+    pub(crate) always_pause_apps: Vec<String>,
+    pub(crate) unknown_apps_pause: UnknownPause,
+    pub(crate) learn_runs: usize,
+    // Viewers pause when the file has fewer lines than this (None = window rows)
+    pub(crate) viewer_pause_rows: Option<u32>,
+    // Command used to show binary files passed to a viewer
+    pub(crate) hex_viewer: Option<String>,
+    // How popups open at the same time are spread out
+    pub(crate) placement: Placement,
+    pub(crate) placement_options: placement::PlacementOptions,
+    // cell_width/cell_height from config, keyed by backend ("" = all backends)
+    pub(crate) cell_overrides: HashMap<String, (Option<u32>, Option<u32>)>,
+    // Derive cell sizes from the terminal's font settings when not configured
+    pub(crate) detect_cell_size: bool,
+    // Reopen apps where and as large as their window was when it last closed
+    pub(crate) remember_geometry: bool,
+    // Reuse the session that has a file open instead of opening it again
+    pub(crate) single_instance: bool,
+    // Desktop notification when a command finishes
+    pub(crate) notify: notify::Notify,
+    // Shell that runs the command (None = bash, or cmd on Windows)
+    pub(crate) shell: Option<shell::Shell>,
+    pub(crate) shell_login: bool,
+    pub(crate) shell_interactive: bool,
+    // cwd, env.NAME, env_remove and path_prepend for every app
+    pub(crate) environment: environment::Environment,
+    // Record launches for `rund history` and `rund again`
+    pub(crate) history: bool,
+    // Append log records at log_level or above to rund.log
+    pub(crate) log_file: bool,
+    pub(crate) log_level: log::Level,
+    // pre_launch, post_exit and on_change commands for every app
+    pub(crate) hooks: hooks::Hooks,
+    // NEW: Per-app geometry configurations
+    pub(crate) app_geometries: std::collections::HashMap<String, AppGeometry>,
+    pub(crate) app_settings: HashMap<String, AppSettings>,
+    #[cfg(target_os = "windows")]
+    pub(crate) terminal: TerminalType,
+}
+
+#[cfg(target_os = "windows")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum TerminalType {
+    Cmd,
+    WindowsTerminal,
+}
+
+/// Whether the popup waits for a key after its command exits
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PauseBehavior {
+    Never,
+    Always,
+    /// Decided per command: editors never, short viewer output and scripts do
+    Auto,
+    /// Pause only when the command exits non-zero
+    OnError,
+    /// Pause when the command exits non-zero or finishes within `fast_exit_secs`
+    OnErrorOrFast,
+}
+
+// How `auto` treats commands that are in none of the app lists
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum UnknownPause {
+    Always,
+    Never,
+    // Pause unless the prompt was dismissed immediately on the last `learn_runs` runs
+    Learn,
+}
+
+impl PauseBehavior {
+    /// "never", "always", "auto", "on_error" or "on_error_or_fast"
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().replace('-', "_").as_str() {
+            "never" => Some(PauseBehavior::Never),
+            "always" => Some(PauseBehavior::Always),
+            "auto" => Some(PauseBehavior::Auto),
+            "on_error" => Some(PauseBehavior::OnError),
+            "on_error_or_fast" => Some(PauseBehavior::OnErrorOrFast),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+impl TerminalType {
+    pub(crate) fn parse(value: &str) -> Self {
+        match value.to_lowercase().as_str() {
+            "wt" | "wt.exe" | "windows_terminal" | "windowsterminal" => {
+                TerminalType::WindowsTerminal
+            }
+            _ => TerminalType::Cmd,
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+impl Default for TerminalType {
+    fn default() -> Self {
+        TerminalType::Cmd
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let backup_dir = if let Ok(exe_path) = rund_exe() {
+            if let Some(exe_dir) = exe_path.parent() {
+                exe_dir.join("backups")
+            } else {
+                PathBuf::from("backups")
+            }
+        } else {
+            PathBuf::from("backups")
+        };
+
+                Config {
+            geometry: AppGeometry::default(),
+            default_app: None,
+            backup_dir,
+            pause_behavior: PauseBehavior::Auto,
+            fast_exit_secs: 3,
+            pause_timeout: None,
+            editor_apps: vec![
+                "vim".to_string(), "nvim".to_string(), "nano".to_string(),
+                "emacs".to_string(), "micro".to_string(), "helix".to_string(),
+                "hx".to_string(), "code".to_string(), "subl".to_string(),
+            ],
+            viewer_apps: vec![
+                "bat".to_string(), "less".to_string(), "more".to_string(),
+                "cat".to_string(), "type".to_string(),
+            ],
+            always_pause_apps: vec![
+                "python".to_string(), "python3".to_string(), "node".to_string(),
+                "ruby".to_string(), "perl".to_string(),
+            ],
+            unknown_apps_pause: UnknownPause::Always,
+            learn_runs: 3,
+            viewer_pause_rows: None,
+            hex_viewer: None,
+            placement: Placement::None,
+            placement_options: placement::PlacementOptions {
+                cascade_offset: 30,
+                tile_columns: 2,
+                tile_rows: 2,
+            },
+            cell_overrides: HashMap::new(),
+            detect_cell_size: false,
+            remember_geometry: false,
+            single_instance: false,
+            notify: notify::Notify::Never,
+            shell: None,
+            shell_login: false,
+            shell_interactive: false,
+            environment: environment::Environment::default(),
+            history: true,
+            log_file: false,
+            log_level: log::Level::Info,
+            hooks: hooks::Hooks::default(),
+            app_geometries: HashMap::new(),
+            app_settings: HashMap::new(),
+            #[cfg(target_os = "windows")]
+            terminal: TerminalType::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Read the config file, writing the default one first if there is none
+    pub fn load() -> io::Result<Config> {
+        load_config()
+    }
+
+    /// Where the config file is: next to the rund executable if it is (or can
+    /// be) there, or else in the user's config directory
+    pub fn path() -> PathBuf {
+        get_config_path()
+    }
+
+    /// The app `rund` runs when none is given
+    pub fn default_app(&self) -> Option<&str> {
+        self.default_app.as_deref()
+    }
+
+    pub fn pause_behavior(&self) -> PauseBehavior {
+        self.pause_behavior
+    }
+
+    pub fn backup_dir(&self) -> &Path {
+        &self.backup_dir
+    }
+
+    /// Copy `file` into the backup directory now, as name_TIMESTAMP.ext
+    pub fn backup(&self, file: &Path) -> io::Result<PathBuf> {
+        create_backup(&file.to_path_buf(), &self.backup_dir)
+    }
+
+    /// The geometry `app` opens with: its own section over the terminal one,
+    /// with percentages and anchors worked out on its monitor
+    pub fn geometry(&self, app: &str) -> AppGeometry {
+        let geom = self.get_geometry(app);
+        let screen = if geom.is_relative() {
+            screen::select(geom.relative.monitor.as_ref())
+        } else {
+            None
+        };
+        let backend = backend_name(self, &RunOptions::default());
+        geom.resolve(screen, self.cell_size(&backend))
+    }
+
+    /// Config from the text of a config file; unknown keys are ignored
+    pub fn parse(content: &str) -> Self {
+        let mut config = Config::default();
+        let mut current_section: Option<String> = None;
+
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            // Check for section headers like [bat]
+            if line.starts_with('[') && line.ends_with(']') {
+                let section = line[1..line.len()-1].to_string();
+                if section == "terminal" {
+                    current_section = None;
+                } else {
+                    current_section = Some(section);
+                }
+                continue;
+            }
+
+            if let Some((key, value)) = line.split_once('=') {
+                let key = key.trim();
+                let value = value.trim().trim_matches('"').trim_matches('\'');
+
+                // If we're in a per-app section, handle geometry and overrides
+                if let Some(ref app_name) = current_section {
+                    let mut geometry = config.app_geometries.get(app_name)
+                        .cloned()
+                        .unwrap_or_else(|| config.geometry.clone());
+                    if geometry.set(key, value) {
+                        config.app_geometries.insert(app_name.clone(), geometry);
+                        continue;
+                    }
+
+                    let settings = config.app_settings.entry(app_name.clone()).or_default();
+                    if settings.environment.set(key, value) || settings.hooks.set(key, value) {
+                        continue;
+                    }
+
+                    match key {
+                        "pause_timeout" => {
+                            if let Ok(v) = value.parse::<u64>() {
+                                settings.pause_timeout = Some(v);
+                            }
+                        }
+                        "remember_geometry" => {
+                            settings.remember_geometry = Some(parse_bool(value));
+                        }
+                        "single_instance" => {
+                            settings.single_instance = Some(parse_bool(value));
+                        }
+                        "notify" => {
+                            settings.notify = notify::Notify::parse(value);
+                        }
+                        "shell" => {
+                            settings.shell = shell::Shell::parse(value);
+                        }
+                        "shell_login" => {
+                            settings.shell_login = Some(parse_bool(value));
+                        }
+                        "shell_interactive" => {
+                            settings.shell_interactive = Some(parse_bool(value));
+                        }
+                        "goto_line" if !value.is_empty() => {
+                            settings.goto_line = Some(value.to_string());
+                        }
+                        _ => {}
+                    }
+                    continue;
+                }
+
+                // Global settings (in [terminal] section or no section)
+                if config.geometry.set(key, value) || config.environment.set(key, value) || config.hooks.set(key, value) {
+                    continue;
+                }
+
+                match key {
+                    "pause_behavior" => {
+                        config.pause_behavior = PauseBehavior::parse(value).unwrap_or_else(|| {
+                            log::warn!(
+                                "invalid pause_behavior '{}' in config.toml: expected never, always, auto, on_error or on_error_or_fast; using auto",
+                                value
+                            );
+                            PauseBehavior::Auto
+                        });
+                    }
+                    "fast_exit_secs" => {
+                        if let Ok(v) = value.parse() {
+                            config.fast_exit_secs = v;
+                        }
+                    }
+                    "pause_timeout" => {
+                        if let Ok(v) = value.parse::<u64>() {
+                            config.pause_timeout = Some(v).filter(|&secs| secs > 0);
+                        }
+                    }
+                    "default_app" if !value.is_empty() => {
+                        config.default_app = Some(value.to_string());
+                    }
+                    "backup_dir" if !value.is_empty() => {
+                        config.backup_dir = PathBuf::from(value);
+                    }
+                    "editor_apps" => {
+                        config.editor_apps = value
+                            .split(',')
+                            .map(|s| s.trim().to_string())
+                            .filter(|s| !s.is_empty())
+                            .collect();
+                    }
+                    "viewer_apps" => {
+                        config.viewer_apps = value
+                            .split(',')
+                            .map(|s| s.trim().to_string())
+                            .filter(|s| !s.is_empty())
+                            .collect();
+                    }
+                    "always_pause_apps" => {
+                        config.always_pause_apps = value
+                            .split(',')
+                            .map(|s| s.trim().to_string())
+                            .filter(|s| !s.is_empty())
+                            .collect();
+                    }
+                    "unknown_apps_pause" => {
+                        config.unknown_apps_pause = match value.to_lowercase().as_str() {
+                            "never" | "false" | "no" => UnknownPause::Never,
+                            "learn" | "learned" => UnknownPause::Learn,
+                            _ => UnknownPause::Always,
+                        };
+                    }
+                    "learn_runs" => {
+                        if let Ok(v) = value.parse::<usize>() {
+                            config.learn_runs = v.max(1);
+                        }
+                    }
+                    "placement" => {
+                        config.placement = Placement::parse(value);
+                    }
+                    "cascade_offset" => {
+                        if let Ok(v) = value.parse() {
+                            config.placement_options.cascade_offset = v;
+                        }
+                    }
+                    "tile_columns" => {
+                        if let Ok(v) = value.parse() {
+                            config.placement_options.tile_columns = v;
+                        }
+                    }
+                    "tile_rows" => {
+                        if let Ok(v) = value.parse() {
+                            config.placement_options.tile_rows = v;
+                        }
+                    }
+                    "detect_cell_size" => {
+                        config.detect_cell_size = parse_bool(value);
+                    }
+                    "remember_geometry" => {
+                        config.remember_geometry = parse_bool(value);
+                    }
+                    "single_instance" => {
+                        config.single_instance = parse_bool(value);
+                    }
+                    "notify" => {
+                        if let Some(when) = notify::Notify::parse(value) {
+                            config.notify = when;
+                        }
+                    }
+                    "shell" => {
+                        config.shell = shell::Shell::parse(value);
+                    }
+                    "shell_login" => {
+                        config.shell_login = parse_bool(value);
+                    }
+                    "shell_interactive" => {
+                        config.shell_interactive = parse_bool(value);
+                    }
+                    "history" => {
+                        config.history = parse_bool(value);
+                    }
+                    "log_file" => {
+                        config.log_file = parse_bool(value);
+                    }
+                    "log_level" => {
+                        if let Some(level) = log::Level::parse(value) {
+                            config.log_level = level;
+                        }
+                    }
+                    // `cell_width = 8` for every backend, `kitty.cell_width = 9` for one
+                    key if key.ends_with("cell_width") || key.ends_with("cell_height") => {
+                        let (backend, metric) = key.rsplit_once('.').unwrap_or(("", key));
+                        if let Ok(v) = value.parse::<u32>() {
+                            let entry = config.cell_overrides.entry(backend.trim().to_lowercase()).or_default();
+                            match metric.trim() {
+                                "cell_width" => entry.0 = Some(v.max(1)),
+                                "cell_height" => entry.1 = Some(v.max(1)),
+                                _ => {}
+                            }
+                        }
+                    }
+                    "viewer_pause_rows" => {
+                        config.viewer_pause_rows = value.parse().ok().filter(|&rows| rows > 0);
+                    }
+                    "hex_viewer" if !value.is_empty() => {
+                        config.hex_viewer = Some(value.to_string());
+                    }
+                    #[cfg(target_os = "windows")]
+                    "terminal" => {
+                        config.terminal = TerminalType::parse(value);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        config
+    }
+
+    // Get non-geometry overrides for specific app (empty if none configured)
+    pub(crate) fn get_settings(&self, app: &str) -> AppSettings {
+        let app_lower = app.to_lowercase();
+        let app_first_word = app_lower.split_whitespace().next().unwrap_or("");
+
+        self.app_settings.get(app_first_word).cloned().unwrap_or_default()
+    }
+
+    // Auto-close countdown for this app; a per-app 0 turns it off again
+    pub(crate) fn pause_timeout(&self, app: &str) -> Option<u64> {
+        match self.get_settings(app).pause_timeout {
+            Some(secs) => Some(secs).filter(|&secs| secs > 0),
+            None => self.pause_timeout,
+        }
+    }
+
+    // Shell for this app's command: --shell, then the app's section, then
+    // [terminal]. None when nothing about the shell was configured.
+    pub(crate) fn shell(&self, app: &str, chosen: Option<&shell::Shell>) -> Option<shell::Shell> {
+        let settings = self.get_settings(app);
+        let login = settings.shell_login.unwrap_or(self.shell_login);
+        let interactive = settings.shell_interactive.unwrap_or(self.shell_interactive);
+        let shell = chosen.cloned().or(settings.shell).or_else(|| self.shell.clone());
+        if shell.is_none() && !login && !interactive {
+            return None;
+        }
+        Some(shell::Shell {
+            login,
+            interactive,
+            ..shell.unwrap_or_default()
+        })
+    }
+
+    pub(crate) fn is_editor(&self, app: &str) -> bool {
+        let app_lower = app.to_lowercase();
+        let app_first_word = app_lower.split_whitespace().next().unwrap_or("");
+        self.editor_apps
+            .iter()
+            .any(|e| app_first_word.contains(&e.to_lowercase()) || app_first_word == e.to_lowercase())
+    }
+
+    // Template for file:line:col arguments: the app's goto_line, or a
+    // built-in one for well-known editors and viewers
+    pub(crate) fn goto_template(&self, app: &str) -> Option<String> {
+        let app_lower = app.to_lowercase();
+        let app_first_word = app_lower.split_whitespace().next().unwrap_or("");
+        let exe = std::path::Path::new(app_first_word)
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or(app_first_word);
+
+        self.get_settings(app)
+            .goto_line
+            .or_else(|| paths::builtin_template(exe).map(str::to_string))
+    }
+
+    pub(crate) fn environment(&self, app: &str) -> environment::Environment {
+        self.environment.merged(&self.get_settings(app).environment)
+    }
+
+    // Name of the config section that applies to the app
+    pub(crate) fn profile(&self, app: &str) -> String {
+        let app_lower = app.to_lowercase();
+        let app_first_word = app_lower.split_whitespace().next().unwrap_or("");
+        if self.app_geometries.contains_key(app_first_word) || self.app_settings.contains_key(app_first_word) {
+            app_first_word.to_string()
+        } else {
+            "terminal".to_string()
+        }
+    }
+
+    pub(crate) fn hooks(&self, app: &str) -> hooks::Hooks {
+        self.hooks.merged(&self.get_settings(app).hooks)
+    }
+
+    pub(crate) fn remember_geometry(&self, app: &str) -> bool {
+        self.get_settings(app).remember_geometry.unwrap_or(self.remember_geometry)
+    }
+
+    pub(crate) fn single_instance(&self, app: &str) -> bool {
+        self.get_settings(app).single_instance.unwrap_or(self.single_instance)
+    }
+
+    pub(crate) fn notify(&self, app: &str) -> notify::Notify {
+        self.get_settings(app).notify.unwrap_or(self.notify)
+    }
+
+    // Line count below which a viewer's output fits without scrolling. Derived
+    // from the popup's rows, leaving a row for the pause prompt.
+    pub(crate) fn viewer_pause_rows(&self, geom: &AppGeometry, cell: cells::CellSize) -> u32 {
+        self.viewer_pause_rows
+            .unwrap_or_else(|| geom.cells(cell).1.saturating_sub(1).max(1))
+    }
+
+    // Cell size for a terminal backend, most specific source first: its own
+    // config keys, `rund --calibrate`, its font settings (if detect_cell_size),
+    // the global config keys, then built-in defaults.
+    pub(crate) fn cell_size(&self, backend: &str) -> cells::CellSize {
+        let builtin = cells::builtin(backend);
+        let global = self.cell_overrides.get("").copied().unwrap_or_default();
+        let fallback = cells::CellSize {
+            width: global.0.unwrap_or(builtin.width),
+            height: global.1.unwrap_or(builtin.height),
+        };
+
+        let base = cells::load_calibrated(&cell_sizes_path())
+            .get(backend)
+            .copied()
+            .or_else(|| {
+                if self.detect_cell_size {
+                    cells::detect_from_font(backend)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or(fallback);
+
+        let own = self.cell_overrides.get(backend).copied().unwrap_or_default();
+        cells::CellSize {
+            width: own.0.unwrap_or(base.width),
+            height: own.1.unwrap_or(base.height),
+        }
+    }
+
+    // Get geometry for specific app, fallback to default
+    pub(crate) fn get_geometry(&self, app: &str) -> AppGeometry {
+        let app_lower = app.to_lowercase();
+        let app_first_word = app_lower.split_whitespace().next().unwrap_or("");
+
+        // Try to find app-specific geometry
+        if let Some(geom) = self.app_geometries.get(app_first_word) {
+            geom.clone()
+        } else {
+            // Fallback to default
+            self.geometry.clone()
+        }
+    }
+}
+
+// The rund executable: the one running, or for other programs using the
+// library the rund on PATH. Wrappers call back into it, and config.toml may
+// sit next to it.
+pub(crate) fn rund_exe() -> io::Result<PathBuf> {
+    let exe = env::current_exe()?;
+    if exe.file_stem().is_some_and(|stem| stem == "rund") {
+        return Ok(exe);
+    }
+    let name = if cfg!(target_os = "windows") { "rund.exe" } else { "rund" };
+    env::var_os("PATH")
+        .and_then(|path| env::split_paths(&path).map(|dir| dir.join(name)).find(|exe| exe.is_file()))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "rund was not found on PATH"))
+}
+
+pub(crate) fn get_config_dir() -> PathBuf {
+    #[cfg(target_os = "windows")]
+    {
+        if let Ok(appdata) = env::var("APPDATA") {
+            return PathBuf::from(appdata).join("rund");
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        if let Ok(home) = env::var("HOME") {
+            return PathBuf::from(home)
+                .join("Library")
+                .join("Application Support")
+                .join("rund");
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        if let Ok(xdg_config) = env::var("XDG_CONFIG_HOME") {
+            return PathBuf::from(xdg_config).join("rund");
+        }
+        if let Ok(home) = env::var("HOME") {
+            return PathBuf::from(home).join(".config").join("rund");
+        }
+    }
+
+    PathBuf::from(".")
+}
+
+pub(crate) fn get_config_path() -> PathBuf {
+    if let Ok(exe_path) = rund_exe() {
+        if let Some(exe_dir) = exe_path.parent() {
+            let config_path = exe_dir.join("config.toml");
+            if config_path.exists() {
+                return config_path;
+            }
+            if let Ok(test_file) = fs::File::create(exe_dir.join(".rund_write_test")) {
+                drop(test_file);
+                fs::remove_file(exe_dir.join(".rund_write_test")).ok();
+                return config_path;
+            }
+        }
+    }
+
+    let config_dir = get_config_dir();
+    fs::create_dir_all(&config_dir).ok();
+    config_dir.join("config.toml")
+}
+
+pub(crate) fn load_config() -> io::Result<Config> {
+    let config_path = get_config_path();
+
+    if !config_path.exists() {
+        #[cfg(target_os = "windows")]
+        let default_config = r#"# rund configuration file
+
+[terminal]
+width = 800
+height = 600
+
+# Position settings
+# If auto_position = true, let Windows decide position
+# If auto_position = false, use x and y below
+auto_position = false
+x = 100
+y = 100
+
+# Sizes and positions may also be relative to the screen, e.g.
+# width = "60%", and an anchor places the window with optional margins:
+# anchor = "center"   # top-left, top, top-right, left, right, bottom-left, bottom, bottom-right
+# margin = 20
+
+# Size in character cells instead of pixels
+# cols = 120
+# rows = 40
+
+# Pixels per character cell, used to turn width/height into columns/rows.
+# Global or per backend ("cmd", "wt"); `rund --calibrate` measures them.
+# cell_width = 8
+# cell_height = 16
+# wt.cell_width = 9
+# wt.cell_height = 19
+# Read the font size from Windows Terminal's settings.json instead
+# detect_cell_size = false
+
+# Monitor to place windows on: "primary", "cursor", "focused",
+# a name or an index from `rund --monitors`
+# monitor = "cursor"
+
+# Spreading out popups that are open at the same time:
+# "none", "cascade", "tile" (grid over the monitor) or "avoid-overlap"
+# placement = "none"
+# cascade_offset = 30
+# tile_columns = 2
+# tile_rows = 2
+
+# Reopen apps where their window was when they last exited (also per app)
+# remember_geometry = false
+
+# Raise the popup that already has a file open instead of opening it again;
+# nvim gets the files over its server (usually set per app)
+# single_instance = false
+
+# Shell running the command (also per app): bash, zsh, fish, sh, pwsh, nu,
+# cmd or a full path; optionally as a login and/or interactive shell
+# shell = "cmd"
+# shell_login = false
+# shell_interactive = false
+
+# Working directory (default: where rund was started) and environment,
+# also per app; one env.NAME line per variable
+# cwd = "~/projects"
+# env.EDITOR = "nvim"
+# env_remove = "PYTHONHOME"
+# path_prepend = "%USERPROFILE%\bin"
+
+# Desktop notification with the command, exit code and duration when a
+# command finishes (also per app): "never", "always", "on_error" or
+# "if_longer_than:30s"
+# notify = "never"
+
+# Record launches for `rund history` and `rund again` (history.jsonl)
+# history = true
+
+# Log what launches do (config, pause decisions, terminals tried and the
+# command line) to rund.log next to this file; log_level is "error", "warn",
+# "info", "debug" or "trace". -v/-vv and RUND_LOG log to the console.
+# log_file = false
+# log_level = "info"
+
+# Hooks, also per app (global ones run first); repeat a key for more commands.
+# pre_launch runs before the popup opens and stops the launch if it fails;
+# post_exit and on_change run as it exits. They get RUND_FILE,
+# RUND_EXIT_CODE, RUND_CHANGED (1/0) and RUND_BACKUP.
+# pre_launch = "if not exist notes mkdir notes"
+# post_exit = "echo %RUND_EXIT_CODE% >> popups.log"
+# on_change = 'git add "%RUND_FILE%"'
+
+# Terminal to use: "cmd" or "wt" (Windows Terminal)
+terminal = "cmd"
+
+# Pause behavior after command execution:
+# "never"  - No pause, window closes immediately
+# "always" - Always pause with "Press any key..."
+# "auto"   - Smart detection based on app lists below
+# "on_error" - Pause only when the command exits with a non-zero code
+# "on_error_or_fast" - Also pause when the command exits within fast_exit_secs
+pause_behavior = "auto"
+fast_exit_secs = 3
+
+# Close the pause prompt automatically after this many seconds
+# (any key keeps the window open). Also settable per app.
+# pause_timeout = 10
+
+# App classifications for smart pause behavior
+# Editors: NEVER pause (they're interactive)
+editor_apps = "vim, nvim, nano, emacs, micro, helix, hx, code, subl"
+
+# Viewers: Pause ONLY for files that fit in the window
+viewer_apps = "bat, less, more, cat, type"
+# Line threshold for viewers (default: rows of the popup window)
+# viewer_pause_rows = 30
+# Binary files given to a viewer open here instead (refused if unset)
+# hex_viewer = "format-hex"
+
+# Always pause: For scripts/interpreters that produce output
+always_pause_apps = "python, python3, node, ruby, perl, php"
+
+# Commands in none of the lists above: "always" (pause), "never", or
+# "learn" - stop pausing once the prompt was dismissed immediately on
+# the last learn_runs runs (remembered in learned_pause.tsv next to this file)
+unknown_apps_pause = "always"
+learn_runs = 3
+
+# Directory for backup files (default: ./backups)
+backup_dir = "backups"
+
+# Uncomment to set default app
+# default_app = "nvim"
+
+# Per-app geometry configuration (optional)
+# Uncomment and customize for specific apps
+# These settings override the default [terminal] geometry
+
+#[bat]
+#width = 1200
+#height = 800
+#x = 200
+#y = 150
+#auto_position = false
+
+#[nvim]
+#width = 1000
+#height = 700
+#x = 100
+#y = 100
+#auto_position = true
+# How file:line:col arguments are passed (built in for common editors)
+#goto_line = "+{line} {file}"
+
+#[python]
+#width = 900
+#height = 600
+#pause_timeout = 10
+"#;
+
+        #[cfg(not(target_os = "windows"))]
+        let default_config = r#"# rund configuration file
+
+[terminal]
+width = 800
+height = 600
+
+# Position settings
+auto_position = false
+x = 100
+y = 100
+
+# Relative to the screen: width = "60%", anchor = "center" | "top-right" | ...
+# margin = 20
+# Size in character cells instead of pixels
+# cols = 120
+# rows = 40
+
+# Pixels per cell for turning width/height into columns/rows, global or per
+# backend (alacritty, kitty, gnome-terminal, konsole, xterm, terminal).
+# Run `rund --calibrate` inside a terminal to measure it.
+# cell_width = 8
+# cell_height = 16
+# kitty.cell_width = 9
+# kitty.cell_height = 19
+# Read font sizes from kitty.conf / alacritty.toml instead
+# detect_cell_size = false
+
+# Monitor: "primary", "cursor", "focused", a name or an index (rund --monitors)
+# monitor = "cursor"
+
+# Spreading out popups that are open at the same time:
+# "none", "cascade", "tile" (grid over the monitor) or "avoid-overlap"
+# placement = "none"
+# cascade_offset = 30
+# tile_columns = 2
+# tile_rows = 2
+
+# Reopen apps where their window was when they last exited (also per app)
+# remember_geometry = false
+
+# Raise the popup that already has a file open instead of opening it again;
+# nvim gets the files over its server (usually set per app)
+# single_instance = false
+
+# Shell running the command (also per app): bash, zsh, fish, sh, pwsh, nu,
+# cmd or a full path; optionally as a login and/or interactive shell
+# shell = "bash"
+# shell_login = false
+# shell_interactive = false
+
+# Working directory (default: where rund was started) and environment,
+# also per app; one env.NAME line per variable
+# cwd = "~/projects"
+# env.EDITOR = "nvim"
+# env_remove = "PYTHONHOME"
+# path_prepend = "~/bin"
+
+# Desktop notification with the command, exit code and duration when a
+# command finishes (also per app): "never", "always", "on_error" or
+# "if_longer_than:30s"
+# notify = "never"
+
+# Record launches for `rund history` and `rund again` (history.jsonl)
+# history = true
+
+# Log what launches do (config, pause decisions, terminals tried and the
+# command line) to rund.log next to this file; log_level is "error", "warn",
+# "info", "debug" or "trace". -v/-vv and RUND_LOG log to the console.
+# log_file = false
+# log_level = "info"
+
+# Hooks, also per app (global ones run first); repeat a key for more commands.
+# pre_launch runs before the popup opens and stops the launch if it fails;
+# post_exit and on_change run as it exits. They get RUND_FILE,
+# RUND_EXIT_CODE, RUND_CHANGED (1/0) and RUND_BACKUP.
+# pre_launch = "mkdir -p notes"
+# post_exit = "echo $RUND_EXIT_CODE >> popups.log"
+# on_change = 'git add "$RUND_FILE"'
+
+# Pause behavior: "never", "always", "auto", "on_error", "on_error_or_fast"
+pause_behavior = "auto"
+# Exits quicker than this count as suspicious for "on_error_or_fast"
+fast_exit_secs = 3
+# Auto-close the pause prompt after N seconds (any key keeps it open)
+# pause_timeout = 10
+
+# App classifications for smart pause behavior
+# Editors: NEVER pause (they're interactive)
+editor_apps = "vim, nvim, nano, emacs, micro, helix, hx, code, subl"
+
+# Viewers: Pause ONLY for files that fit in the window
+viewer_apps = "bat, less, more, cat"
+# Line threshold for viewers (default: rows of the popup window)
+# viewer_pause_rows = 30
+# Binary files given to a viewer open here instead (refused if unset)
+# hex_viewer = "hexyl"
+
+# Always pause: For scripts/interpreters that produce output
+always_pause_apps = "python, python3, node, ruby, perl, php"
+
+# Commands in none of the lists above: "always" (pause), "never", or
+# "learn" - stop pausing once the prompt was dismissed immediately on
+# the last learn_runs runs (remembered in learned_pause.tsv next to this file)
+unknown_apps_pause = "always"
+learn_runs = 3
+
+# Directory for backup files (default: ./backups)
+backup_dir = "backups"
+
+# Uncomment to set default app
+# default_app = "nvim"
+
+# Per-app sections override the settings above, e.g. how an editor is told
+# to open file:line:col arguments (built in for common editors):
+#[micro]
+#goto_line = "+{line}:{col} {file}"
+"#;
+
+        log::info!("writing the default config to {}", config_path.display());
+        fs::write(&config_path, default_config)?;
+    }
+
+    let content = fs::read_to_string(&config_path)?;
+    let config = Config::parse(&content);
+    log::set_file(config.log_file.then(log_path), config.log_level);
+    // Where get_config_path found (or put) it
+    let exe_dir = rund_exe().ok().and_then(|exe| exe.parent().map(Path::to_path_buf));
+    let place = if exe_dir.as_deref() == config_path.parent() {
+        "next to the rund executable"
+    } else {
+        "in the user config directory"
+    };
+    log::info!("config: {} ({})", config_path.display(), place);
+    log::debug!(
+        "pause_behavior {:?}, unknown apps {:?}, placement {:?}, history {}",
+        config.pause_behavior,
+        config.unknown_apps_pause,
+        config.placement,
+        config.history
+    );
+    Ok(config)
+}
+
+// Learned pause state lives next to the config: one "exe<TAB>0|1" line per
+// prompt, appended by the wrappers, where 1 means it was dismissed within a second.
+pub(crate) fn learned_pause_path() -> PathBuf {
+    get_config_path().with_file_name("learned_pause.tsv")
+}
+
+// Cell sizes measured by `rund --calibrate`, next to the config
+pub(crate) fn cell_sizes_path() -> PathBuf {
+    get_config_path().with_file_name("cell_sizes.tsv")
+}
+
+// Last window geometry per app (remember_geometry), next to the config:
+// one "app<TAB>x<TAB>y<TAB>width<TAB>height" line per app
+pub(crate) fn window_geometry_path() -> PathBuf {
+    get_config_path().with_file_name("window_geometry.tsv")
+}
+
+// Launch history (see history.rs), next to the config
+pub(crate) fn history_path() -> PathBuf {
+    get_config_path().with_file_name("history.jsonl")
+}
+
+// log_file = true (see log.rs), next to the config
+pub(crate) fn log_path() -> PathBuf {
+    get_config_path().with_file_name("rund.log")
+}
+
+pub(crate) fn remembered_geometry(app: &str) -> Option<screen::Rect> {
+    let content = fs::read_to_string(window_geometry_path()).ok()?;
+    content.lines().rev().find_map(|line| {
+        let mut fields = line.split('\t');
+        if fields.next()? != app {
+            return None;
+        }
+        let mut number = || fields.next()?.trim().parse::<i64>().ok();
+        let (x, y, width, height) = (number()?, number()?, number()?, number()?);
+        if width <= 0 || height <= 0 {
+            return None;
+        }
+        Some(screen::Rect { x: x as i32, y: y as i32, width: width as u32, height: height as u32 })
+    })
+}
+
+// Replace the app's line, keeping the others
+pub(crate) fn save_window_geometry(app: &str, rect: screen::Rect) -> io::Result<()> {
+    let path = window_geometry_path();
+    let content = fs::read_to_string(&path).unwrap_or_default();
+    let mut lines: Vec<String> = content
+        .lines()
+        .filter(|line| line.split('\t').next() != Some(app))
+        .map(str::to_string)
+        .collect();
+    lines.push(format!("{}\t{}\t{}\t{}\t{}", app, rect.x, rect.y, rect.width, rect.height));
+    fs::write(&path, lines.join("\n") + "\n")
+}
+
+// Pause unless the last `runs` prompts for this executable were all dismissed
+// immediately. Until enough runs are recorded the safe default (pause) wins.
+pub(crate) fn learned_pause(exe: &str, runs: usize) -> bool {
+    let path = learned_pause_path();
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(_) => return true,
+    };
+
+    let mut history: HashMap<&str, Vec<bool>> = HashMap::new();
+    let mut total = 0;
+    for line in content.lines() {
+        if let Some((name, quick)) = line.split_once('\t') {
+            history.entry(name).or_default().push(quick.trim() == "1");
+            total += 1;
+        }
+    }
+
+    let decision = match history.get(exe) {
+        Some(quick) if quick.len() >= runs => !quick[quick.len() - runs..].iter().all(|&q| q),
+        _ => true,
+    };
+
+    // Compact once the log grows well past what is needed
+    if total > history.len() * runs * 4 {
+        let mut compacted = String::new();
+        for (name, quick) in &history {
+            for q in &quick[quick.len().saturating_sub(runs)..] {
+                compacted.push_str(&format!("{}\t{}\n", name, if *q { 1 } else { 0 }));
+            }
+        }
+        fs::write(&path, compacted).ok();
+    }
+
+    decision
+}
@@ -0,0 +1,267 @@
+// src/daemon.rs - `rund daemon`: keeps the config loaded and takes requests
+// over a Unix socket, one JSON object per line each way:
+//   {"request":"launch","args":["nvim","notes.md"],"cwd":"/home/me","env":{"PATH":"..."}}
+//   {"request":"list"}
+//   {"request":"kill","session":"2"}    (also "focus")
+//   {"request":"reload"}
+// Every request gets {"ok":true,...} or {"ok":false,"error":"..."} back.
+// Each client is served on its own thread; the config is reloaded as soon as
+// its file changes.
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::json::{self, Value};
+use crate::config::{get_config_path, load_config, Config};
+use crate::launch::launch_command;
+use crate::{cli, log, runtime, watch};
+
+// A client that sends nothing for this long is dropped
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub fn socket_path() -> io::Result<PathBuf> {
+    Ok(runtime::runtime_dir()?.join("daemon.sock"))
+}
+
+// Shared by the client threads, each of which only holds the lock to pick up
+// the current config; launches run with it released
+struct State {
+    config: Arc<Config>,
+    // Config file modification time the loaded config is from
+    loaded: Option<SystemTime>,
+}
+
+fn config_modified() -> Option<SystemTime> {
+    fs::metadata(get_config_path()).and_then(|m| m.modified()).ok()
+}
+
+impl State {
+    fn reload(&mut self) -> io::Result<()> {
+        self.config = Arc::new(load_config()?);
+        self.loaded = config_modified();
+        Ok(())
+    }
+
+    // Pick up config edits: called when the file changes, and before each
+    // request in case watching it failed
+    fn refresh(&mut self) {
+        if config_modified() != self.loaded {
+            match self.reload() {
+                Ok(()) => log::info!("Config reloaded"),
+                // Keep serving with the last good config
+                Err(e) => log::warn!("Config reload failed: {}", e),
+            }
+        }
+    }
+}
+
+fn lock(state: &Mutex<State>) -> MutexGuard<'_, State> {
+    state.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn error(message: impl ToString) -> Value {
+    Value::object(vec![("ok", false.into()), ("error", message.to_string().into())])
+}
+
+fn ok(mut pairs: Vec<(&str, Value)>) -> Value {
+    pairs.insert(0, ("ok", true.into()));
+    Value::object(pairs)
+}
+
+fn session_json(session: &runtime::Session) -> Value {
+    Value::object(vec![
+        ("id", session.id.as_str().into()),
+        ("pid", session.pid.map(i64::from).into()),
+        ("backend", session.backend.as_str().into()),
+        ("app", session.app.as_str().into()),
+        ("title", session.title.as_str().into()),
+        (
+            "files",
+            session.files.iter().map(|f| f.display().to_string()).collect::<Vec<_>>().into(),
+        ),
+        ("x", i64::from(session.rect.x).into()),
+        ("y", i64::from(session.rect.y).into()),
+        ("width", i64::from(session.rect.width).into()),
+        ("height", i64::from(session.rect.height).into()),
+        ("started", (session.created as i64).into()),
+    ])
+}
+
+fn launch(request: &Value, config: &Config) -> Result<(), String> {
+    let args: Vec<String> = request
+        .get("args")
+        .and_then(Value::as_array)
+        .ok_or("launch needs \"args\"")?
+        .iter()
+        .map(|arg| arg.as_str().map(str::to_string).ok_or("\"args\" must be strings"))
+        .collect::<Result<_, _>>()?;
+    let (mut options, app, app_args) = match cli::parse(&args)? {
+        cli::Invocation::Run { options, app, args } => (*options, app, args),
+        _ => return Err("launch only runs apps; use the other requests for the rest".to_string()),
+    };
+    if options.from_quickfix {
+        return Err("quickfix input is read from the client's stdin; run rund qf directly".to_string());
+    }
+    // Relative paths in the request are relative to the client, and the
+    // popup gets the client's environment
+    if let Some(cwd) = request.get("cwd").and_then(Value::as_str) {
+        if !PathBuf::from(cwd).is_dir() {
+            return Err(format!("{}: not a directory", cwd));
+        }
+        options.client_dir = Some(PathBuf::from(cwd));
+    }
+    if let Some(Value::Object(vars)) = request.get("env") {
+        let vars = vars
+            .iter()
+            .map(|(name, value)| value.as_str().map(|value| (name.clone(), value.to_string())))
+            .collect::<Option<Vec<_>>>()
+            .ok_or("\"env\" values must be strings")?;
+        options.client_env = Some(vars);
+    }
+    let app = app
+        .or_else(|| config.default_app.clone())
+        .ok_or("No app specified and no default_app in config")?;
+    launch_command(&app, &app_args, config, &options)
+        .map(|_| ())
+        .map_err(|e| format!("Failed to run terminal: {}", e))
+}
+
+fn respond(line: &str, state: &Mutex<State>) -> Value {
+    let request = match json::parse(line) {
+        Some(request) => request,
+        None => return error("not a JSON object"),
+    };
+    // Launches can take a while (pre_launch hooks), so they work on a
+    // snapshot of the config instead of keeping other clients waiting
+    let config = {
+        let mut state = lock(state);
+        state.refresh();
+        Arc::clone(&state.config)
+    };
+
+    let target = || request.get("session").and_then(Value::as_str).ok_or("this request needs \"session\"");
+    let with_session = |action: fn(&runtime::Session) -> io::Result<()>| -> Result<(), String> {
+        let sessions = runtime::sessions();
+        let target = target()?;
+        let session = runtime::find_session(&sessions, target)
+            .ok_or_else(|| format!("No running session matches '{}'", target))?;
+        action(session).map_err(|e| e.to_string())
+    };
+
+    let result = match request.get("request").and_then(Value::as_str) {
+        Some("launch") => launch(&request, &config).map(|_| Vec::new()),
+        Some("list") => {
+            let sessions: Vec<Value> = runtime::sessions().iter().map(session_json).collect();
+            Ok(vec![("sessions", Value::Array(sessions))])
+        }
+        Some("kill") => with_session(runtime::kill).map(|_| Vec::new()),
+        Some("focus") => with_session(runtime::focus).map(|_| Vec::new()),
+        Some("reload") => lock(state).reload().map(|_| Vec::new()).map_err(|e| e.to_string()),
+        Some(other) => Err(format!("unknown request '{}'", other)),
+        None => Err("missing \"request\"".to_string()),
+    };
+    match result {
+        Ok(pairs) => ok(pairs),
+        Err(e) => error(e),
+    }
+}
+
+// Requests on one connection are answered in order until the client hangs up
+fn serve_client(stream: UnixStream, state: &Mutex<State>) -> io::Result<()> {
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = respond(&line, state);
+        writeln!(writer, "{}", response)?;
+    }
+    Ok(())
+}
+
+// Reload the config whenever its file changes, so mistakes show up at once
+fn watch_config(state: Arc<Mutex<State>>) {
+    let mut changes = match watch::FileChanges::new(&get_config_path()) {
+        Ok(changes) => changes,
+        Err(e) => {
+            log::warn!("Not watching the config ({}); it is checked before each request instead", e);
+            return;
+        }
+    };
+    while changes.wait().is_ok() {
+        lock(&state).refresh();
+    }
+}
+
+pub fn serve() -> io::Result<()> {
+    let path = socket_path()?;
+    if UnixStream::connect(&path).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            format!("A rund daemon is already listening on {}", path.display()),
+        ));
+    }
+    // Left behind by a daemon that did not exit cleanly
+    fs::remove_file(&path).ok();
+    let listener = UnixListener::bind(&path)?;
+
+    let state = Arc::new(Mutex::new(State { config: Arc::new(load_config()?), loaded: config_modified() }));
+    let watched = Arc::clone(&state);
+    thread::spawn(move || watch_config(watched));
+    println!("rund daemon listening on {}", path.display());
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let state = Arc::clone(&state);
+                thread::spawn(move || {
+                    if let Err(e) = serve_client(stream, &state) {
+                        log::warn!("Client error: {}", e);
+                    }
+                });
+            }
+            Err(e) => log::warn!("Accept failed: {}", e),
+        }
+    }
+    Ok(())
+}
+
+// Hand a launch to a running daemon. None when there is none, so the
+// caller launches by itself.
+pub fn forward(args: &[String]) -> Option<io::Result<()>> {
+    // An unsafe runtime directory is reported when launching by ourselves
+    let stream = UnixStream::connect(socket_path().ok()?).ok()?;
+    // Variables that are not valid UTF-8 cannot go into the request
+    let vars: Vec<(String, String)> = env::vars_os()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+        .collect();
+    let request = Value::object(vec![
+        ("request", "launch".into()),
+        ("args", args.to_vec().into()),
+        ("cwd", env::current_dir().ok().map(|cwd| cwd.display().to_string()).into()),
+        ("env", Value::object(vars.iter().map(|(name, value)| (name.as_str(), value.as_str().into())).collect())),
+    ]);
+
+    let exchange = || -> io::Result<Value> {
+        stream.set_read_timeout(Some(Duration::from_secs(30)))?;
+        writeln!(&stream, "{}", request)?;
+        let mut response = String::new();
+        BufReader::new(&stream).read_line(&mut response)?;
+        json::parse(&response)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "unreadable response from the rund daemon"))
+    };
+    let response = match exchange() {
+        Ok(response) => response,
+        Err(e) => return Some(Err(e)),
+    };
+    match response.get("error").and_then(Value::as_str) {
+        Some(message) => Some(Err(io::Error::new(io::ErrorKind::Other, message.to_string()))),
+        None => Some(Ok(())),
+    }
+}
@@ -0,0 +1,522 @@
+// src/dbus.rs - Just enough of the D-Bus wire protocol to make a method call
+// on the session bus and read back its reply: connect, authenticate as the
+// current user, say Hello, call, and wait for the answer
+use std::env;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::time::Duration;
+
+const METHOD_CALL: u8 = 1;
+const METHOD_RETURN: u8 = 2;
+const ERROR: u8 = 3;
+
+// Header field codes
+const PATH: u8 = 1;
+const INTERFACE: u8 = 2;
+const MEMBER: u8 = 3;
+const ERROR_NAME: u8 = 4;
+const REPLY_SERIAL: u8 = 5;
+const DESTINATION: u8 = 6;
+const SIGNATURE: u8 = 8;
+
+fn bad_message(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("D-Bus: {}", what))
+}
+
+// Marshalled data, little-endian. Offsets count from the start of the
+// message, so alignment works for the header and (as the header is padded to
+// 8 bytes) for a body written separately.
+#[derive(Default)]
+pub struct Writer {
+    buf: Vec<u8>,
+}
+
+impl Writer {
+    fn align(&mut self, n: usize) {
+        while self.buf.len() % n != 0 {
+            self.buf.push(0);
+        }
+    }
+
+    pub fn byte(&mut self, value: u8) {
+        self.buf.push(value);
+    }
+
+    pub fn u32(&mut self, value: u32) {
+        self.align(4);
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn i32(&mut self, value: i32) {
+        self.align(4);
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    // Also used for object paths
+    pub fn string(&mut self, value: &str) {
+        self.u32(value.len() as u32);
+        self.buf.extend_from_slice(value.as_bytes());
+        self.buf.push(0);
+    }
+
+    pub fn signature(&mut self, value: &str) {
+        self.buf.push(value.len() as u8);
+        self.buf.extend_from_slice(value.as_bytes());
+        self.buf.push(0);
+    }
+
+    // An array whose elements align to `element_align`; `items` writes them
+    pub fn array(&mut self, element_align: usize, items: impl FnOnce(&mut Writer)) {
+        self.u32(0);
+        let length_at = self.buf.len() - 4;
+        self.align(element_align);
+        let start = self.buf.len();
+        items(self);
+        let length = (self.buf.len() - start) as u32;
+        self.buf[length_at..length_at + 4].copy_from_slice(&length.to_le_bytes());
+    }
+
+    // A dict entry or struct: aligned to 8
+    pub fn structure(&mut self, fields: impl FnOnce(&mut Writer)) {
+        self.align(8);
+        fields(self);
+    }
+}
+
+pub struct Call<'a> {
+    pub destination: &'a str,
+    pub path: &'a str,
+    pub interface: &'a str,
+    pub member: &'a str,
+    pub signature: &'a str,
+    pub body: Writer,
+}
+
+fn message(serial: u32, call: &Call) -> Vec<u8> {
+    let mut m = Writer::default();
+    m.byte(b'l');
+    m.byte(METHOD_CALL);
+    m.byte(0);
+    m.byte(1);
+    m.u32(call.body.buf.len() as u32);
+    m.u32(serial);
+
+    let string_field = |m: &mut Writer, code: u8, kind: &str, value: &str| {
+        m.structure(|m| {
+            m.byte(code);
+            m.signature(kind);
+            m.string(value);
+        })
+    };
+    m.array(8, |m| {
+        string_field(m, PATH, "o", call.path);
+        string_field(m, INTERFACE, "s", call.interface);
+        string_field(m, MEMBER, "s", call.member);
+        string_field(m, DESTINATION, "s", call.destination);
+        if !call.signature.is_empty() {
+            m.structure(|m| {
+                m.byte(SIGNATURE);
+                m.signature("g");
+                m.signature(call.signature);
+            });
+        }
+    });
+    m.align(8);
+    m.buf.extend_from_slice(&call.body.buf);
+    m.buf
+}
+
+// The parts of a received message needed to match it to a call
+struct Reply {
+    kind: u8,
+    reply_serial: Option<u32>,
+    error_name: Option<String>,
+}
+
+fn read_message(stream: &mut impl Read) -> io::Result<Reply> {
+    let mut fixed = [0u8; 16];
+    stream.read_exact(&mut fixed)?;
+    let little_endian = match fixed[0] {
+        b'l' => true,
+        b'B' => false,
+        _ => return Err(bad_message("unknown byte order")),
+    };
+    let number = |bytes: &[u8]| {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        if little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        }
+    };
+    let body_len = number(&fixed[4..8]) as usize;
+    let fields_len = number(&fixed[12..16]) as usize;
+    if body_len > 1 << 24 || fields_len > 1 << 16 {
+        return Err(bad_message("message too large"));
+    }
+
+    let header_end = (16 + fields_len + 7) / 8 * 8;
+    let mut rest = vec![0u8; header_end - 16 + body_len];
+    stream.read_exact(&mut rest)?;
+    // The body is read to get to the next message, but not needed
+    let mut header = fixed.to_vec();
+    header.extend_from_slice(&rest[..header_end - 16]);
+
+    let mut reply = Reply { kind: fixed[1], reply_serial: None, error_name: None };
+    let mut pos = 16;
+    let fields_end = 16 + fields_len;
+    let truncated = || bad_message("truncated header");
+    while pos < fields_end {
+        pos = (pos + 7) / 8 * 8;
+        let code = *header.get(pos).ok_or_else(truncated)?;
+        let sig_len = *header.get(pos + 1).ok_or_else(truncated)? as usize;
+        let kind = *header.get(pos + 2).ok_or_else(truncated)?;
+        pos += 3 + sig_len;
+        match kind {
+            b'u' => {
+                pos = (pos + 3) / 4 * 4;
+                let value = number(header.get(pos..pos + 4).ok_or_else(truncated)?);
+                if code == REPLY_SERIAL {
+                    reply.reply_serial = Some(value);
+                }
+                pos += 4;
+            }
+            b's' | b'o' => {
+                pos = (pos + 3) / 4 * 4;
+                let len = number(header.get(pos..pos + 4).ok_or_else(truncated)?) as usize;
+                let text = header.get(pos + 4..pos + 4 + len).ok_or_else(truncated)?;
+                if code == ERROR_NAME {
+                    reply.error_name = Some(String::from_utf8_lossy(text).into_owned());
+                }
+                pos += 4 + len + 1;
+            }
+            b'g' => {
+                let len = *header.get(pos).ok_or_else(truncated)? as usize;
+                pos += 1 + len + 1;
+            }
+            _ => return Err(bad_message("unexpected header field type")),
+        }
+    }
+    Ok(reply)
+}
+
+// unix:path=... or unix:abstract=..., the first usable one of a
+// ;-separated list; values are %-escaped
+fn session_bus() -> io::Result<UnixStream> {
+    let address = match env::var("DBUS_SESSION_BUS_ADDRESS") {
+        Ok(address) => address,
+        Err(_) => match env::var_os("XDG_RUNTIME_DIR") {
+            Some(dir) => format!("unix:path={}", PathBuf::from(dir).join("bus").display()),
+            None => return Err(io::Error::new(io::ErrorKind::NotFound, "No D-Bus session bus")),
+        },
+    };
+    let unescape = |value: &str| {
+        let bytes = value.as_bytes();
+        let mut out = Vec::new();
+        let mut i = 0;
+        while i < bytes.len() {
+            match (bytes[i], value.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok())) {
+                (b'%', Some(byte)) => {
+                    out.push(byte);
+                    i += 3;
+                }
+                (byte, _) => {
+                    out.push(byte);
+                    i += 1;
+                }
+            }
+        }
+        out
+    };
+
+    let mut last_error = io::Error::new(io::ErrorKind::NotFound, format!("Unsupported D-Bus address '{}'", address));
+    for entry in address.split(';') {
+        let params = match entry.strip_prefix("unix:") {
+            Some(params) => params,
+            None => continue,
+        };
+        for param in params.split(',') {
+            let connected = match param.split_once('=') {
+                Some(("path", path)) => {
+                    UnixStream::connect(PathBuf::from(String::from_utf8_lossy(&unescape(path)).into_owned()))
+                }
+                #[cfg(any(target_os = "linux", target_os = "android"))]
+                Some(("abstract", name)) => {
+                    use std::os::linux::net::SocketAddrExt;
+                    std::os::unix::net::SocketAddr::from_abstract_name(unescape(name))
+                        .and_then(|addr| UnixStream::connect_addr(&addr))
+                }
+                _ => continue,
+            };
+            match connected {
+                Ok(stream) => return Ok(stream),
+                Err(e) => last_error = e,
+            }
+        }
+    }
+    Err(last_error)
+}
+
+extern "C" {
+    fn getuid() -> u32;
+}
+
+// SASL EXTERNAL: the bus checks the uid we claim against the socket's peer
+fn authenticate(stream: &mut UnixStream) -> io::Result<()> {
+    let uid = unsafe { getuid() }.to_string();
+    let hex: String = uid.bytes().map(|b| format!("{:02x}", b)).collect();
+    stream.write_all(format!("\0AUTH EXTERNAL {}\r\n", hex).as_bytes())?;
+
+    let mut line = String::new();
+    BufReader::new(&mut *stream).read_line(&mut line)?;
+    if !line.starts_with("OK ") {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("D-Bus authentication failed: {}", line.trim()),
+        ));
+    }
+    stream.write_all(b"BEGIN\r\n")
+}
+
+// Make `call` on the session bus and wait until it succeeded or failed
+pub fn call(call: &Call) -> io::Result<()> {
+    let mut stream = session_bus()?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    authenticate(&mut stream)?;
+
+    let hello = Call {
+        destination: "org.freedesktop.DBus",
+        path: "/org/freedesktop/DBus",
+        interface: "org.freedesktop.DBus",
+        member: "Hello",
+        signature: "",
+        body: Writer::default(),
+    };
+    stream.write_all(&message(1, &hello))?;
+    stream.write_all(&message(2, call))?;
+
+    // The Hello reply and signals like NameAcquired come first
+    loop {
+        let reply = read_message(&mut stream)?;
+        match (reply.kind, reply.reply_serial) {
+            (METHOD_RETURN, Some(2)) => return Ok(()),
+            (ERROR, Some(serial)) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    format!(
+                        "{} failed: {}",
+                        if serial == 1 { "Hello" } else { call.member },
+                        reply.error_name.unwrap_or_default()
+                    ),
+                ))
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::net::UnixListener;
+    use std::sync::Mutex;
+    use std::thread::{self, JoinHandle};
+
+    // Tests that point DBUS_SESSION_BUS_ADDRESS somewhere take turns
+    pub static BUS: Mutex<()> = Mutex::new(());
+
+    pub struct Received {
+        pub member: String,
+        pub serial: u32,
+        pub signature: String,
+        pub body: Vec<u8>,
+    }
+
+    // What a stand-in bus saw: the AUTH line and the calls after BEGIN
+    pub struct Session {
+        pub auth: String,
+        pub calls: Vec<Received>,
+    }
+
+    // Reads marshalled values back, as the bus would
+    pub struct Reader<'a> {
+        pub buf: &'a [u8],
+        pub pos: usize,
+    }
+
+    impl Reader<'_> {
+        fn align(&mut self, n: usize) {
+            self.pos = (self.pos + n - 1) / n * n;
+        }
+
+        pub fn u32(&mut self) -> u32 {
+            self.align(4);
+            let bytes = &self.buf[self.pos..self.pos + 4];
+            self.pos += 4;
+            u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+        }
+
+        pub fn string(&mut self) -> String {
+            let len = self.u32() as usize;
+            let text = String::from_utf8(self.buf[self.pos..self.pos + len].to_vec()).unwrap();
+            self.pos += len + 1;
+            text
+        }
+
+        pub fn signature(&mut self) -> String {
+            let len = self.buf[self.pos] as usize;
+            let text = String::from_utf8(self.buf[self.pos + 1..self.pos + 1 + len].to_vec()).unwrap();
+            self.pos += len + 2;
+            text
+        }
+    }
+
+    fn read_call(stream: &mut UnixStream) -> Option<Received> {
+        let mut fixed = [0u8; 16];
+        stream.read_exact(&mut fixed).ok()?;
+        assert_eq!((fixed[0], fixed[1]), (b'l', METHOD_CALL));
+        let mut reader = Reader { buf: &fixed, pos: 4 };
+        let (body_len, serial, fields_len) = (reader.u32() as usize, reader.u32(), reader.u32() as usize);
+        let header_end = (16 + fields_len + 7) / 8 * 8;
+        let mut message = fixed.to_vec();
+        message.resize(header_end + body_len, 0);
+        stream.read_exact(&mut message[16..]).unwrap();
+
+        let mut received = Received { member: String::new(), serial, signature: String::new(), body: Vec::new() };
+        let mut fields = Reader { buf: &message, pos: 16 };
+        while fields.pos < 16 + fields_len {
+            fields.align(8);
+            let code = fields.buf[fields.pos];
+            fields.pos += 1;
+            match fields.signature().as_str() {
+                "s" | "o" => {
+                    let value = fields.string();
+                    if code == MEMBER {
+                        received.member = value;
+                    }
+                }
+                "g" => received.signature = fields.signature(),
+                other => panic!("unexpected header field type {}", other),
+            }
+        }
+        received.body = message[header_end..].to_vec();
+        Some(received)
+    }
+
+    // One line of the handshake; byte by byte, since messages follow BEGIN
+    // right away
+    fn line(stream: &mut UnixStream) -> String {
+        let mut line = Vec::new();
+        let mut byte = [0u8];
+        while !line.ends_with(b"\r\n") {
+            stream.read_exact(&mut byte).unwrap();
+            line.push(byte[0]);
+        }
+        String::from_utf8(line).unwrap()
+    }
+
+    fn reply(kind: u8, reply_serial: u32, error_name: Option<&str>) -> Vec<u8> {
+        let mut m = Writer::default();
+        m.byte(b'l');
+        m.byte(kind);
+        m.byte(1);
+        m.byte(1);
+        m.u32(0);
+        m.u32(1000 + reply_serial);
+        m.array(8, |m| {
+            m.structure(|m| {
+                m.byte(REPLY_SERIAL);
+                m.signature("u");
+                m.u32(reply_serial);
+            });
+            if let Some(name) = error_name {
+                m.structure(|m| {
+                    m.byte(ERROR_NAME);
+                    m.signature("s");
+                    m.string(name);
+                });
+            }
+        });
+        m.align(8);
+        m.buf
+    }
+
+    // A session bus stand-in for one connection, at a socket in a directory
+    // of its own that DBUS_SESSION_BUS_ADDRESS points to (hold BUS) until the
+    // client hangs up. Calls to `refuse` fail as if nobody owned the name.
+    pub fn fake_bus(name: &str, refuse: Option<&'static str>) -> JoinHandle<Session> {
+        let dir = env::temp_dir().join(format!("rund-test-bus-{}-{}", std::process::id(), name));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("bus");
+        fs::remove_file(&path).ok();
+        let listener = UnixListener::bind(&path).unwrap();
+        let previous = env::var_os("DBUS_SESSION_BUS_ADDRESS");
+        env::set_var("DBUS_SESSION_BUS_ADDRESS", format!("unix:path={}", path.display()));
+
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let auth = line(&mut stream);
+            stream.write_all(b"OK 0123456789abcdef0123456789abcdef\r\n").unwrap();
+            assert_eq!(line(&mut stream), "BEGIN\r\n");
+
+            let mut calls = Vec::new();
+            while let Some(call) = read_call(&mut stream) {
+                let answer = match refuse {
+                    Some(member) if call.member == member => {
+                        reply(ERROR, call.serial, Some("org.freedesktop.DBus.Error.ServiceUnknown"))
+                    }
+                    _ => reply(METHOD_RETURN, call.serial, None),
+                };
+                stream.write_all(&answer).unwrap();
+                calls.push(call);
+            }
+            match previous {
+                Some(address) => env::set_var("DBUS_SESSION_BUS_ADDRESS", address),
+                None => env::remove_var("DBUS_SESSION_BUS_ADDRESS"),
+            }
+            fs::remove_dir_all(&dir).ok();
+            Session { auth, calls }
+        })
+    }
+
+    fn ping() -> Call<'static> {
+        Call {
+            destination: "org.example.Test",
+            path: "/org/example/Test",
+            interface: "org.example.Test",
+            member: "Ping",
+            signature: "s",
+            body: {
+                let mut body = Writer::default();
+                body.string("hello");
+                body
+            },
+        }
+    }
+
+    #[test]
+    fn authenticates_says_hello_and_calls() {
+        let _bus = BUS.lock().unwrap_or_else(|e| e.into_inner());
+        let bus = fake_bus("call", None);
+        call(&ping()).unwrap();
+        let session = bus.join().unwrap();
+
+        let uid: String = unsafe { getuid() }.to_string().bytes().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(session.auth, format!("\0AUTH EXTERNAL {}\r\n", uid));
+        let members: Vec<(&str, u32)> = session.calls.iter().map(|c| (c.member.as_str(), c.serial)).collect();
+        assert_eq!(members, [("Hello", 1), ("Ping", 2)]);
+        assert_eq!(session.calls[1].signature, "s");
+        assert_eq!(Reader { buf: &session.calls[1].body, pos: 0 }.string(), "hello");
+    }
+
+    #[test]
+    fn errors_name_the_call() {
+        let _bus = BUS.lock().unwrap_or_else(|e| e.into_inner());
+        let bus = fake_bus("error", Some("Ping"));
+        let error = call(&ping()).unwrap_err();
+        bus.join().unwrap();
+        assert_eq!(error.to_string(), "Ping failed: org.freedesktop.DBus.Error.ServiceUnknown");
+    }
+}
@@ -0,0 +1,135 @@
+// src/environment.rs - Working directory and environment for launched commands
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Environment {
+    pub cwd: Option<PathBuf>,
+    // `env.NAME = value`, in config order
+    pub set: Vec<(String, String)>,
+    pub remove: Vec<String>,
+    pub path_prepend: Vec<String>,
+}
+
+// "~" and "~/..." relative to the home directory
+pub fn expand_home(value: &str) -> PathBuf {
+    let home = std::env::var_os(if cfg!(target_os = "windows") { "USERPROFILE" } else { "HOME" });
+    match (value.strip_prefix('~'), home) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with(['/', '\\']) => {
+            PathBuf::from(home).join(rest.trim_start_matches(['/', '\\']))
+        }
+        _ => PathBuf::from(value),
+    }
+}
+
+// Only names every shell accepts in an assignment
+fn valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+impl Environment {
+    // Apply one key from [terminal] or an [app] section. Returns false for
+    // keys that are not about the environment.
+    pub fn set(&mut self, key: &str, value: &str) -> bool {
+        match key {
+            "cwd" => self.cwd = Some(value).filter(|v| !v.is_empty()).map(expand_home),
+            "env_remove" => self.remove = list(value).into_iter().filter(|name| valid_name(name)).collect(),
+            "path_prepend" => {
+                self.path_prepend = list(value)
+                    .iter()
+                    .map(|dir| expand_home(dir).display().to_string())
+                    .collect()
+            }
+            key => match key.strip_prefix("env.") {
+                Some(name) if valid_name(name.trim()) => {
+                    let name = name.trim().to_string();
+                    self.set.retain(|(n, _)| *n != name);
+                    self.set.push((name, value.to_string()));
+                }
+                Some(_) => {}
+                None => return false,
+            },
+        }
+        true
+    }
+
+    // An app's section on top of [terminal]: its cwd wins, its variables are
+    // set after the global ones and its PATH entries come first
+    pub fn merged(&self, app: &Environment) -> Environment {
+        let mut set = self.set.clone();
+        set.retain(|(name, _)| !app.set.iter().any(|(n, _)| n == name));
+        set.extend(app.set.iter().cloned());
+
+        let mut remove = self.remove.clone();
+        remove.extend(app.remove.iter().filter(|name| !self.remove.contains(name)).cloned());
+
+        Environment {
+            cwd: app.cwd.clone().or_else(|| self.cwd.clone()),
+            set,
+            remove,
+            path_prepend: app.path_prepend.iter().chain(&self.path_prepend).cloned().collect(),
+        }
+    }
+
+    // Whether the cmd wrapper needs a batch file for it
+    #[cfg(target_os = "windows")]
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty() && self.remove.is_empty() && self.path_prepend.is_empty()
+    }
+
+    // Directory the command starts in: --cwd, the configured one, or
+    // `current`, made absolute so every backend agrees on it
+    pub fn working_dir(&self, chosen: Option<&Path>, current: PathBuf) -> std::io::Result<PathBuf> {
+        let dir = match chosen.or(self.cwd.as_deref()) {
+            Some(dir) => current.join(dir),
+            None => return Ok(current),
+        };
+        if !dir.is_dir() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("Working directory {} does not exist", dir.display()),
+            ));
+        }
+        Ok(dunce::canonicalize(&dir).unwrap_or(dir))
+    }
+
+    // Statements that set up the environment in the POSIX sh wrapper
+    #[cfg(not(target_os = "windows"))]
+    pub fn sh_script(&self, quote: impl Fn(&str) -> String) -> String {
+        let mut script = String::new();
+        if !self.remove.is_empty() {
+            script.push_str(&format!("unset {}; ", self.remove.join(" ")));
+        }
+        for (name, value) in &self.set {
+            script.push_str(&format!("export {}={}; ", name, quote(value)));
+        }
+        if !self.path_prepend.is_empty() {
+            let dirs: Vec<String> = self.path_prepend.iter().map(|dir| quote(dir)).collect();
+            script.push_str(&format!("export PATH={}:\"$PATH\"; ", dirs.join(":")));
+        }
+        script
+    }
+
+    // Lines that set up the environment in the cmd wrapper batch file;
+    // %VAR% references in values expand when the batch runs
+    #[cfg(target_os = "windows")]
+    pub fn cmd_lines(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self.remove.iter().map(|name| format!("set \"{}=\"", name)).collect();
+        for (name, value) in &self.set {
+            lines.push(format!("set \"{}={}\"", name, value));
+        }
+        if !self.path_prepend.is_empty() {
+            lines.push(format!("set \"PATH={};%PATH%\"", self.path_prepend.join(";")));
+        }
+        lines
+    }
+}
@@ -0,0 +1,315 @@
+// src/glob.rs - Glob patterns for --watch and the .gitignore rules that
+// filter what is watched
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+// `{a,b}` alternatives spelled out, one pattern each (not nested)
+fn expand_braces(pattern: &str) -> Vec<String> {
+    let (open, close) = match (pattern.find('{'), pattern.find('}')) {
+        (Some(open), Some(close)) if open < close => (open, close),
+        _ => return vec![pattern.to_string()],
+    };
+    pattern[open + 1..close]
+        .split(',')
+        .flat_map(|choice| expand_braces(&format!("{}{}{}", &pattern[..open], choice, &pattern[close + 1..])))
+        .collect()
+}
+
+// One path component against `*`, `?` and `[...]` (`[!...]` negated)
+fn match_component(pattern: &[char], name: &[char]) -> bool {
+    match pattern.first() {
+        None => name.is_empty(),
+        Some('*') => (0..=name.len()).any(|skip| match_component(&pattern[1..], &name[skip..])),
+        Some('?') => !name.is_empty() && match_component(&pattern[1..], &name[1..]),
+        Some('[') => {
+            let close = match pattern.iter().skip(2).position(|&c| c == ']') {
+                Some(at) => at + 2,
+                // No closing bracket: a literal '['
+                None => return name.first() == Some(&'[') && match_component(&pattern[1..], &name[1..]),
+            };
+            let c = match name.first() {
+                Some(&c) => c,
+                None => return false,
+            };
+            let (negated, set) = match pattern[1] {
+                '!' | '^' => (true, &pattern[2..close]),
+                _ => (false, &pattern[1..close]),
+            };
+            let mut found = false;
+            let mut i = 0;
+            while i < set.len() {
+                if i + 2 < set.len() && set[i + 1] == '-' {
+                    found |= (set[i]..=set[i + 2]).contains(&c);
+                    i += 3;
+                } else {
+                    found |= set[i] == c;
+                    i += 1;
+                }
+            }
+            found != negated && match_component(&pattern[close + 1..], &name[1..])
+        }
+        Some('\\') if pattern.len() > 1 => name.first() == Some(&pattern[1]) && match_component(&pattern[2..], &name[1..]),
+        Some(&p) => name.first() == Some(&p) && match_component(&pattern[1..], &name[1..]),
+    }
+}
+
+// `**` as a whole component matches any number of directories
+fn match_components(pattern: &[Vec<char>], path: &[Vec<char>]) -> bool {
+    match pattern.first() {
+        None => path.is_empty(),
+        Some(p) if p.iter().collect::<String>() == "**" => {
+            (0..=path.len()).any(|skip| match_components(&pattern[1..], &path[skip..]))
+        }
+        Some(p) => !path.is_empty() && match_component(p, &path[0]) && match_components(&pattern[1..], &path[1..]),
+    }
+}
+
+fn components(path: &str) -> Vec<Vec<char>> {
+    path.split('/').filter(|c| !c.is_empty()).map(|c| c.chars().collect()).collect()
+}
+
+fn is_wild(component: &str) -> bool {
+    component.contains(['*', '?', '[', '{'])
+}
+
+// A compiled glob: the directory it starts in and the patterns below it
+#[derive(Debug, Clone)]
+pub struct Glob {
+    pub base: PathBuf,
+    patterns: Vec<Vec<Vec<char>>>,
+}
+
+impl Glob {
+    // `src/**/*.rs`, `*.{py,toml}`, `/etc/app/*.conf`; relative ones start in `cwd`
+    pub fn new(pattern: &str, cwd: &Path) -> Glob {
+        let mut pattern = pattern.replace('\\', "/");
+        // A directory stands for everything in it
+        if !is_wild(&pattern) && cwd.join(&pattern).is_dir() {
+            pattern = format!("{}/**", pattern.trim_end_matches('/'));
+        }
+        let parts: Vec<&str> = pattern.split('/').collect();
+        let fixed = parts.iter().take_while(|part| !is_wild(part)).count();
+        // A pattern without wildcards names a file: watch it in its directory
+        let fixed = fixed.min(parts.len() - 1);
+
+        let base = parts[..fixed].join("/");
+        let base = match base.as_str() {
+            "" if pattern.starts_with('/') => PathBuf::from("/"),
+            "" => cwd.to_path_buf(),
+            base => cwd.join(base),
+        };
+        let rest = parts[fixed..].join("/");
+        Glob {
+            base,
+            patterns: expand_braces(&rest).iter().map(|p| components(p)).collect(),
+        }
+    }
+
+    pub fn matches(&self, path: &Path) -> bool {
+        let relative = match path.strip_prefix(&self.base) {
+            Ok(relative) => relative,
+            Err(_) => return false,
+        };
+        let relative: Vec<Vec<char>> = relative
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(name) => Some(name.to_string_lossy().chars().collect()),
+                _ => None,
+            })
+            .collect();
+        self.patterns.iter().any(|pattern| match_components(pattern, &relative))
+    }
+}
+
+// One line of a .gitignore
+#[derive(Debug)]
+struct Rule {
+    pattern: Vec<Vec<char>>,
+    negated: bool,
+    dir_only: bool,
+}
+
+// The rules of one .gitignore (or .git/info/exclude), for paths below `dir`
+#[derive(Debug)]
+struct IgnoreFile {
+    dir: PathBuf,
+    rules: Vec<Rule>,
+}
+
+fn parse_ignore(dir: &Path, content: &str) -> IgnoreFile {
+    let mut rules = Vec::new();
+    for line in content.lines() {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        // A slash other than at the end anchors the pattern to the file's
+        // directory; without one it matches a name at any depth
+        let pattern = match line.strip_prefix('/') {
+            Some(anchored) => anchored.to_string(),
+            None if line.contains('/') => line.to_string(),
+            None => format!("**/{}", line),
+        };
+        rules.push(Rule { pattern: components(&pattern), negated, dir_only });
+    }
+    IgnoreFile { dir: dir.to_path_buf(), rules }
+}
+
+// What git would ignore. .gitignore files are read from the repository root
+// down, as directories are visited.
+#[derive(Debug, Default)]
+pub struct Ignore {
+    files: Vec<IgnoreFile>,
+    visited: Vec<PathBuf>,
+}
+
+impl Ignore {
+    // Rules that apply above `dir`: from the enclosing git repository's root
+    // (and its .git/info/exclude) down to `dir` itself
+    pub fn for_dir(dir: &Path) -> Ignore {
+        let mut ignore = Ignore::default();
+        let root = dir.ancestors().find(|d| d.join(".git").exists());
+        if let Some(root) = root {
+            if let Ok(content) = fs::read_to_string(root.join(".git/info/exclude")) {
+                ignore.files.push(parse_ignore(root, &content));
+            }
+            let mut chain: Vec<&Path> = dir.ancestors().take_while(|d| *d != root).collect();
+            chain.push(root);
+            for d in chain.into_iter().rev() {
+                ignore.visit(d);
+            }
+        } else {
+            ignore.visit(dir);
+        }
+        ignore
+    }
+
+    // Pick up the .gitignore of a directory being walked
+    pub fn visit(&mut self, dir: &Path) {
+        if self.visited.iter().any(|d| d == dir) {
+            return;
+        }
+        self.visited.push(dir.to_path_buf());
+        if let Ok(content) = fs::read_to_string(dir.join(".gitignore")) {
+            self.files.push(parse_ignore(dir, &content));
+        }
+    }
+
+    // The last matching rule wins, deeper files after shallower ones
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        if path.file_name().is_some_and(|name| name == ".git") {
+            return true;
+        }
+        let mut ignored = false;
+        let mut files: Vec<&IgnoreFile> = self.files.iter().filter(|f| path.starts_with(&f.dir)).collect();
+        files.sort_by_key(|f| f.dir.components().count());
+        for file in files {
+            let relative = match path.strip_prefix(&file.dir) {
+                Ok(relative) => components(&relative.to_string_lossy().replace('\\', "/")),
+                Err(_) => continue,
+            };
+            for rule in &file.rules {
+                if (!rule.dir_only || is_dir) && match_components(&rule.pattern, &relative) {
+                    ignored = !rule.negated;
+                }
+            }
+        }
+        ignored
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn matches(pattern: &str, path: &str) -> bool {
+        Glob::new(pattern, Path::new("/p")).matches(Path::new(path))
+    }
+
+    #[test]
+    fn double_star_spans_directories() {
+        assert_eq!(Glob::new("src/**/*.rs", Path::new("/p")).base, PathBuf::from("/p/src"));
+        assert!(matches("src/**/*.rs", "/p/src/main.rs"));
+        assert!(matches("src/**/*.rs", "/p/src/a/b/c.rs"));
+        assert!(!matches("src/**/*.rs", "/p/src/main.rsx"));
+        assert!(!matches("src/**/*.rs", "/p/tests/main.rs"));
+        assert!(matches("**/Cargo.toml", "/p/Cargo.toml"));
+        assert!(matches("**/Cargo.toml", "/p/crates/x/Cargo.toml"));
+        assert!(matches("docs/**", "/p/docs/a/b.md"));
+        // Only a whole component: `a**` is `a*`
+        assert!(!matches("src/a**", "/p/src/ab/c"));
+    }
+
+    #[test]
+    fn character_classes_and_alternatives() {
+        assert!(matches("log[0-9].txt", "/p/log7.txt"));
+        assert!(!matches("log[0-9].txt", "/p/logs.txt"));
+        assert!(matches("[!.]*", "/p/visible"));
+        assert!(!matches("[!.]*", "/p/.hidden"));
+        assert!(matches("[^.]*", "/p/visible"));
+        assert!(matches("[ab-d]?", "/p/c1"));
+        assert!(!matches("[ab-d]?", "/p/e1"));
+        assert!(!matches("[ab-d]?", "/p/c"));
+        // No closing bracket: a literal '['
+        assert!(matches("a[b", "/p/a[b"));
+        assert!(matches("*.{py,toml}", "/p/setup.py"));
+        assert!(matches("*.{py,toml}", "/p/pyproject.toml"));
+        assert!(!matches("*.{py,toml}", "/p/setup.cfg"));
+    }
+
+    #[test]
+    fn patterns_are_anchored() {
+        // At the base: a `*` does not reach into directories
+        assert!(matches("*.rs", "/p/lib.rs"));
+        assert!(!matches("*.rs", "/p/src/lib.rs"));
+        assert!(!matches("*.rs", "/elsewhere/lib.rs"));
+        // And must match all of the name
+        assert!(!matches("*.rs", "/p/lib.rs.orig"));
+        assert!(!matches("lib", "/p/libs"));
+        // Absolute patterns start at their own directory
+        let conf = Glob::new("/etc/app/*.conf", Path::new("/p"));
+        assert_eq!(conf.base, PathBuf::from("/etc/app"));
+        assert!(conf.matches(Path::new("/etc/app/main.conf")));
+        assert!(!conf.matches(Path::new("/p/etc/app/main.conf")));
+    }
+
+    #[test]
+    fn gitignore_rules() {
+        let root = env::temp_dir().join(format!("rund-test-ignore-{}", std::process::id()));
+        fs::create_dir_all(root.join(".git/info")).unwrap();
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join(".git/info/exclude"), "*.swp\n").unwrap();
+        fs::write(root.join(".gitignore"), "# logs\n*.log\n!keep.log\n/build/\ntarget\n\\!bang\n").unwrap();
+        fs::write(root.join("sub/.gitignore"), "!*.log\nkeep.log\n").unwrap();
+
+        let ignore = Ignore::for_dir(&root.join("sub"));
+        let ignored = |path: &str, is_dir: bool| ignore.is_ignored(&root.join(path), is_dir);
+        assert!(ignored("a.log", false));
+        assert!(ignored("deep/er/a.log", false));
+        // Negated, and the last matching rule wins
+        assert!(!ignored("keep.log", false));
+        assert!(!ignored("deep/keep.log", false));
+        // A deeper .gitignore overrides the ones above it
+        assert!(!ignored("sub/a.log", false));
+        assert!(ignored("sub/keep.log", false));
+        // Anchored to the .gitignore's directory, and for directories only
+        assert!(ignored("build", true));
+        assert!(!ignored("build", false));
+        assert!(!ignored("sub/build", true));
+        assert!(ignored("sub/target", true));
+        assert!(ignored("!bang", false));
+        assert!(ignored("notes.swp", false));
+        assert!(ignored(".git", true));
+        assert!(!ignored("src/main.rs", false));
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,227 @@
+// src/history.rs - Launch history: one JSON object per line, appended when a
+// launch starts, when it exits and when a file it edited is backed up
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::json::{self, Value};
+
+// Window size in pixels; no position when the window manager placed it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Geometry {
+    pub x: Option<i32>,
+    pub y: Option<i32>,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Entry {
+    pub id: String,
+    pub started: u64,
+    // Directory the command ran in
+    pub cwd: String,
+    // The command as run, file arguments already made absolute
+    pub command: String,
+    // rund's own options for the launch, given again by `rund again`
+    pub options: Vec<String>,
+    // Config section that applied ("terminal" when the app has none)
+    pub profile: String,
+    pub geometry: Option<Geometry>,
+    // Files the app was opened on
+    pub files: Vec<String>,
+    pub ended: Option<u64>,
+    pub exit_code: Option<i64>,
+    pub backups: Vec<String>,
+}
+
+fn append(path: &Path, value: Value) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", value)
+}
+
+pub fn record_launch(path: &Path, entry: &Entry) -> io::Result<()> {
+    let geometry = match entry.geometry {
+        Some(g) => Value::object(vec![
+            ("x", g.x.map(i64::from).into()),
+            ("y", g.y.map(i64::from).into()),
+            ("width", i64::from(g.width).into()),
+            ("height", i64::from(g.height).into()),
+        ]),
+        None => Value::Null,
+    };
+    append(
+        path,
+        Value::object(vec![
+            ("event", "launch".into()),
+            ("id", entry.id.as_str().into()),
+            ("started", (entry.started as i64).into()),
+            ("cwd", entry.cwd.as_str().into()),
+            ("command", entry.command.as_str().into()),
+            ("options", entry.options.clone().into()),
+            ("profile", entry.profile.as_str().into()),
+            ("geometry", geometry),
+            ("files", entry.files.clone().into()),
+        ]),
+    )
+}
+
+pub fn record_exit(path: &Path, id: &str, ended: u64, exit_code: Option<i64>) -> io::Result<()> {
+    append(
+        path,
+        Value::object(vec![
+            ("event", "exit".into()),
+            ("id", id.into()),
+            ("ended", (ended as i64).into()),
+            ("exit_code", exit_code.into()),
+        ]),
+    )
+}
+
+pub fn record_backup(path: &Path, id: &str, backup: &Path) -> io::Result<()> {
+    append(
+        path,
+        Value::object(vec![
+            ("event", "backup".into()),
+            ("id", id.into()),
+            ("path", backup.display().to_string().into()),
+        ]),
+    )
+}
+
+// A launch whose terminal never started; load() leaves it out
+pub fn record_failed(path: &Path, id: &str) -> io::Result<()> {
+    append(path, Value::object(vec![("event", "failed".into()), ("id", id.into())]))
+}
+
+fn parse_geometry(value: &Value) -> Option<Geometry> {
+    let number = |key: &str| value.get(key).and_then(Value::as_i64);
+    Some(Geometry {
+        x: number("x").map(|x| x as i32),
+        y: number("y").map(|y| y as i32),
+        width: number("width")? as u32,
+        height: number("height")? as u32,
+    })
+}
+
+// Launches oldest first, with what was later learned about them filled in.
+// Lines that do not parse (say, a half-written one) are skipped.
+pub fn load(path: &Path) -> Vec<Entry> {
+    let content = fs::read_to_string(path).unwrap_or_default();
+    let mut entries: Vec<Entry> = Vec::new();
+
+    for line in content.lines() {
+        let record = match json::parse(line) {
+            Some(record) => record,
+            None => continue,
+        };
+        let text = |key: &str| record.get(key).and_then(Value::as_str).unwrap_or("").to_string();
+        let strings = |key: &str| -> Vec<String> {
+            let items = record.get(key).and_then(Value::as_array).unwrap_or_default();
+            items.iter().filter_map(|v| v.as_str().map(str::to_string)).collect()
+        };
+        let id = text("id");
+
+        match record.get("event").and_then(Value::as_str) {
+            Some("launch") => entries.push(Entry {
+                started: record.get("started").and_then(Value::as_i64).unwrap_or(0) as u64,
+                cwd: text("cwd"),
+                command: text("command"),
+                options: strings("options"),
+                profile: text("profile"),
+                geometry: record.get("geometry").and_then(parse_geometry),
+                files: strings("files"),
+                id,
+                ..Entry::default()
+            }),
+            Some("exit") => {
+                if let Some(entry) = entries.iter_mut().rev().find(|e| e.id == id) {
+                    entry.ended = record.get("ended").and_then(Value::as_i64).map(|t| t as u64);
+                    entry.exit_code = record.get("exit_code").and_then(Value::as_i64);
+                }
+            }
+            Some("failed") => entries.retain(|e| e.id != id),
+            Some("backup") => {
+                if let Some(entry) = entries.iter_mut().rev().find(|e| e.id == id) {
+                    entry.backups.push(text("path"));
+                }
+            }
+            _ => {}
+        }
+    }
+    entries
+}
+
+// "40s ago", "5m ago", "3h ago", "2d ago"
+pub fn age(secs: u64) -> String {
+    match secs {
+        0..=59 => format!("{}s ago", secs),
+        60..=3599 => format!("{}m ago", secs / 60),
+        3600..=86399 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn scratch(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("rund-history-{}-{}.jsonl", std::process::id(), name));
+        fs::remove_file(&path).ok();
+        path
+    }
+
+    #[test]
+    fn records_are_merged_into_launches() {
+        let path = scratch("merge");
+        let launch = |id: &str, started: u64| Entry {
+            id: id.to_string(),
+            started,
+            cwd: "/home/me/app".to_string(),
+            command: "nvim 'a b.txt'".to_string(),
+            options: vec!["--title".to_string(), "notes".to_string()],
+            profile: "nvim".to_string(),
+            geometry: Some(Geometry { x: None, y: Some(-20), width: 800, height: 600 }),
+            files: vec!["/home/me/app/a b.txt".to_string()],
+            ..Entry::default()
+        };
+        record_launch(&path, &launch("one", 100)).unwrap();
+        record_launch(&path, &launch("two", 200)).unwrap();
+        record_backup(&path, "one", Path::new("/backups/a b.txt.1")).unwrap();
+        record_exit(&path, "one", 150, Some(3)).unwrap();
+        record_exit(&path, "two", 250, None).unwrap();
+        // A half-written last line from a crash is skipped
+        OpenOptions::new().append(true).open(&path).unwrap().write_all(b"{\"event\":\"exit\",\"id\":").unwrap();
+
+        let entries = load(&path);
+        fs::remove_file(&path).ok();
+        assert_eq!(entries.len(), 2);
+        let (one, two) = (&entries[0], &entries[1]);
+        assert_eq!((one.id.as_str(), one.started, one.ended, one.exit_code), ("one", 100, Some(150), Some(3)));
+        assert_eq!((two.id.as_str(), two.ended, two.exit_code), ("two", Some(250), None));
+        assert_eq!(one.command, "nvim 'a b.txt'");
+        assert_eq!(one.options, ["--title", "notes"]);
+        assert_eq!(one.files, ["/home/me/app/a b.txt"]);
+        assert_eq!(one.geometry, Some(Geometry { x: None, y: Some(-20), width: 800, height: 600 }));
+        assert_eq!(one.backups, ["/backups/a b.txt.1"]);
+        assert!(two.backups.is_empty());
+    }
+
+    #[test]
+    fn missing_file_is_empty() {
+        assert!(load(&scratch("missing")).is_empty());
+    }
+
+    #[test]
+    fn ages() {
+        let cases = [(0, "0s ago"), (59, "59s ago"), (60, "1m ago"), (3599, "59m ago"), (7200, "2h ago"), (172800, "2d ago")];
+        for (secs, expected) in cases {
+            assert_eq!(age(secs), expected);
+        }
+    }
+}
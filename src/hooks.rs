@@ -0,0 +1,289 @@
+// src/hooks.rs - pre_launch, post_exit and on_change commands, and the work
+// left for when a popup exits
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use sha2::{Digest, Sha256};
+
+use crate::config::history_path;
+use crate::json::{self, Value};
+use crate::notify::{self, Notify};
+use crate::{history, log, runtime};
+
+// Shell commands from [terminal] or an app section; a key given more than
+// once adds another command
+#[derive(Debug, Clone, Default)]
+pub struct Hooks {
+    pub pre_launch: Vec<String>,
+    pub post_exit: Vec<String>,
+    pub on_change: Vec<String>,
+}
+
+impl Hooks {
+    // Returns false for keys that are not hooks
+    pub fn set(&mut self, key: &str, value: &str) -> bool {
+        let list = match key {
+            "pre_launch" => &mut self.pre_launch,
+            "post_exit" => &mut self.post_exit,
+            "on_change" => &mut self.on_change,
+            _ => return false,
+        };
+        if !value.is_empty() {
+            list.push(value.to_string());
+        }
+        true
+    }
+
+    // [terminal]'s hooks run first, then the app's
+    pub fn merged(&self, app: &Hooks) -> Hooks {
+        let join = |global: &[String], own: &[String]| global.iter().chain(own).cloned().collect();
+        Hooks {
+            pre_launch: join(&self.pre_launch, &app.pre_launch),
+            post_exit: join(&self.post_exit, &app.post_exit),
+            on_change: join(&self.on_change, &app.on_change),
+        }
+    }
+}
+
+pub fn calculate_file_hash(path: &PathBuf) -> io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 8192];
+
+    loop {
+        let n = file.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+pub fn create_backup(file_path: &PathBuf, backup_dir: &PathBuf) -> io::Result<PathBuf> {
+    fs::create_dir_all(backup_dir)?;
+
+    let file_name = file_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("file");
+
+    let file_ext = file_path
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(|s| format!(".{}", s))
+        .unwrap_or_default();
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap();
+    let timestamp = now.as_secs();
+
+    let backup_name = format!("{}_{}{}", file_name, timestamp, file_ext);
+    let backup_path = backup_dir.join(backup_name);
+
+    fs::copy(file_path, &backup_path)?;
+
+    Ok(backup_path)
+}
+
+// Run a hook through the platform shell in `cwd`, with the context in
+// RUND_* variables on top of `base_env` (rund's own environment when None);
+// returns its exit code
+pub fn run(command: &str, cwd: &Path, vars: &[(&str, String)], base_env: Option<&[(String, String)]>) -> io::Result<i32> {
+    let mut shell = if cfg!(target_os = "windows") {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C");
+        cmd
+    } else {
+        let mut sh = Command::new("sh");
+        sh.arg("-c");
+        sh
+    };
+    if let Some(base_env) = base_env {
+        shell.env_clear().envs(base_env.iter().map(|(name, value)| (name, value)));
+    }
+    let status = shell
+        .arg(command)
+        .current_dir(cwd)
+        .envs(vars.iter().map(|(name, value)| (*name, value.as_str())))
+        .status()?;
+    Ok(status.code().unwrap_or(-1))
+}
+
+// What is left to do once a popup exits. Saved in the runtime directory at
+// launch and picked up by `rund --record-exit`, which the wrapper runs as it
+// exits, on every platform.
+#[derive(Debug, Clone, Default)]
+pub struct Completion {
+    pub id: String,
+    pub cwd: PathBuf,
+    // The command as launched and when, for the notification
+    pub command: String,
+    pub started: u64,
+    pub notify: Notify,
+    // File the app was opened on, with its hash at launch ("" = missing)
+    pub file: Option<PathBuf>,
+    pub hash: String,
+    // Set for -c/-o files, which are backed up when they changed
+    pub backup_dir: Option<PathBuf>,
+    pub post_exit: Vec<String>,
+    pub on_change: Vec<String>,
+    // Whether the launch is in the history, to record the exit there
+    pub history: bool,
+}
+
+fn completion_path(id: &str) -> io::Result<PathBuf> {
+    Ok(runtime::runtime_dir()?.join(format!("completion-{}.json", id)))
+}
+
+fn path_value(path: &Option<PathBuf>) -> Value {
+    path.as_ref().map(|p| p.display().to_string()).into()
+}
+
+impl Completion {
+    pub fn is_needed(&self) -> bool {
+        self.history
+            || self.notify != Notify::Never
+            || !self.post_exit.is_empty()
+            || (self.file.is_some() && (self.backup_dir.is_some() || !self.on_change.is_empty()))
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let value = Value::object(vec![
+            ("id", self.id.as_str().into()),
+            ("cwd", self.cwd.display().to_string().into()),
+            ("command", self.command.as_str().into()),
+            ("started", (self.started as i64).into()),
+            ("notify", self.notify.to_string().into()),
+            ("file", path_value(&self.file)),
+            ("hash", self.hash.as_str().into()),
+            ("backup_dir", path_value(&self.backup_dir)),
+            ("post_exit", self.post_exit.clone().into()),
+            ("on_change", self.on_change.clone().into()),
+            ("history", self.history.into()),
+        ]);
+        fs::write(completion_path(&self.id)?, value.to_string())
+    }
+
+    // For a launch that never started
+    pub fn discard(id: &str) {
+        if let Ok(path) = completion_path(id) {
+            fs::remove_file(path).ok();
+        }
+    }
+
+    // Taken out of the runtime directory, so it is only done once
+    pub fn take(id: &str) -> Option<Completion> {
+        // Not from a runtime directory someone else could have written to
+        let path = completion_path(id).ok()?;
+        let value = json::parse(&fs::read_to_string(&path).ok()?)?;
+        fs::remove_file(&path).ok();
+
+        let text = |key: &str| value.get(key).and_then(Value::as_str).map(str::to_string);
+        let list = |key: &str| -> Vec<String> {
+            let items = value.get(key).and_then(Value::as_array).unwrap_or_default();
+            items.iter().filter_map(|v| v.as_str().map(str::to_string)).collect()
+        };
+        Some(Completion {
+            id: id.to_string(),
+            cwd: PathBuf::from(text("cwd")?),
+            command: text("command").unwrap_or_default(),
+            started: value.get("started").and_then(Value::as_i64).unwrap_or(0) as u64,
+            notify: text("notify").and_then(|when| Notify::parse(&when)).unwrap_or_default(),
+            file: text("file").map(PathBuf::from),
+            hash: text("hash").unwrap_or_default(),
+            backup_dir: text("backup_dir").map(PathBuf::from),
+            post_exit: list("post_exit"),
+            on_change: list("on_change"),
+            history: value.get("history") == Some(&Value::Bool(true)),
+        })
+    }
+
+    // Record the exit, notify, back up a changed -c/-o file and run the
+    // hooks. Whatever fails is reported but does not stop the steps after it;
+    // without a backup the hooks get an empty RUND_BACKUP.
+    pub fn finish(&self, exit_code: Option<i64>) -> io::Result<()> {
+        let ended = runtime::now_secs();
+        if self.history {
+            if let Err(e) = history::record_exit(&history_path(), &self.id, ended, exit_code) {
+                log::warn!("Could not record the exit in the history: {}", e);
+            }
+        }
+        let secs = ended.saturating_sub(self.started);
+        if self.notify.wanted(exit_code, secs) {
+            if let Err(e) = notify::completed(&self.command, exit_code, secs) {
+                log::warn!("Could not send the notification: {}", e);
+            }
+        }
+
+        let changed = match self.file {
+            Some(ref file) => calculate_file_hash(file).unwrap_or_default() != self.hash,
+            None => false,
+        };
+        let mut backup = None;
+        if let (true, Some(file), Some(dir)) = (changed, &self.file, &self.backup_dir) {
+            if file.exists() {
+                match create_backup(file, dir) {
+                    Ok(path) => {
+                        log::info!("Backup created: {}", path.display());
+                        if self.history {
+                            history::record_backup(&history_path(), &self.id, &path).ok();
+                        }
+                        backup = Some(path);
+                    }
+                    Err(e) => log::warn!("Could not back up {}: {}", file.display(), e),
+                }
+            }
+        }
+
+        let vars = [
+            ("RUND_FILE", self.file.as_ref().map(|f| f.display().to_string()).unwrap_or_default()),
+            ("RUND_EXIT_CODE", exit_code.map(|code| code.to_string()).unwrap_or_default()),
+            ("RUND_CHANGED", if changed { "1" } else { "0" }.to_string()),
+            ("RUND_BACKUP", backup.map(|b| b.display().to_string()).unwrap_or_default()),
+        ];
+        let on_change = if changed { &self.on_change[..] } else { &[] };
+        for command in self.post_exit.iter().chain(on_change) {
+            match run(command, &self.cwd, &vars, None) {
+                Ok(0) => {}
+                Ok(code) => log::warn!("Hook '{}' exited with code {}", command, code),
+                Err(e) => log::warn!("Hook '{}' failed: {}", command, e),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn hooks_run_when_the_backup_fails() {
+        let dir = std::env::temp_dir().join(format!("rund-test-finish-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("notes.md");
+        fs::write(&file, "edited").unwrap();
+        // A file where the backup directory should be
+        let blocked = dir.join("backups");
+        fs::write(&blocked, "").unwrap();
+
+        let completion = Completion {
+            id: "finish-test".to_string(),
+            cwd: dir.clone(),
+            file: Some(file),
+            hash: String::new(),
+            backup_dir: Some(blocked),
+            post_exit: vec!["echo \"[$RUND_CHANGED $RUND_BACKUP]\" > hook.out".to_string()],
+            ..Completion::default()
+        };
+        completion.finish(Some(0)).unwrap();
+        assert_eq!(fs::read_to_string(dir.join("hook.out")).unwrap(), "[1 ]\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
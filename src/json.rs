@@ -0,0 +1,347 @@
+// src/json.rs - Minimal JSON values for the history file and daemon messages
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    // Keys in insertion order
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub fn object(pairs: Vec<(&str, Value)>) -> Value {
+        Value::Object(pairs.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(pairs) => pairs.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Number(n) if n.fract() == 0.0 => Some(*n as i64),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(n: i64) -> Self {
+        Value::Number(n as f64)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::Null, Into::into)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(items: Vec<T>) -> Self {
+        Value::Array(items.into_iter().map(Into::into).collect())
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    f.write_str("\"")
+}
+
+// Compact, single-line output, so every value fits on one JSON line
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Null => f.write_str("null"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Number(n) if n.fract() == 0.0 && n.abs() < 1e15 => write!(f, "{}", *n as i64),
+            Value::Number(n) if n.is_finite() => write!(f, "{}", n),
+            Value::Number(_) => f.write_str("null"),
+            Value::String(s) => write_string(f, s),
+            Value::Array(items) => {
+                f.write_str("[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                f.write_str("]")
+            }
+            Value::Object(pairs) => {
+                f.write_str("{")?;
+                for (i, (key, value)) in pairs.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+// Arrays and objects nested deeper than this are refused rather than parsed
+// with ever more stack; rund's own records nest two levels
+const MAX_DEPTH: usize = 64;
+
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    // Arrays and objects the parser is inside of
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.peek().is_some_and(|c| c.is_whitespace()) {
+            self.chars.next();
+        }
+    }
+
+    fn expect(&mut self, word: &str) -> Option<()> {
+        for c in word.chars() {
+            if self.chars.next()? != c {
+                return None;
+            }
+        }
+        Some(())
+    }
+
+    fn value(&mut self) -> Option<Value> {
+        self.skip_whitespace();
+        if matches!(self.chars.peek(), Some('[' | '{')) {
+            if self.depth == MAX_DEPTH {
+                return None;
+            }
+            self.depth += 1;
+            let value = self.nested();
+            self.depth -= 1;
+            return value;
+        }
+        self.scalar()
+    }
+
+    fn nested(&mut self) -> Option<Value> {
+        match self.chars.next()? {
+            '[' => {
+                let mut items = Vec::new();
+                self.skip_whitespace();
+                if self.chars.peek() == Some(&']') {
+                    self.chars.next();
+                    return Some(Value::Array(items));
+                }
+                loop {
+                    items.push(self.value()?);
+                    self.skip_whitespace();
+                    match self.chars.next()? {
+                        ',' => continue,
+                        ']' => return Some(Value::Array(items)),
+                        _ => return None,
+                    }
+                }
+            }
+            '{' => {
+                let mut pairs = Vec::new();
+                self.skip_whitespace();
+                if self.chars.peek() == Some(&'}') {
+                    self.chars.next();
+                    return Some(Value::Object(pairs));
+                }
+                loop {
+                    self.skip_whitespace();
+                    let key = self.string()?;
+                    self.skip_whitespace();
+                    if self.chars.next()? != ':' {
+                        return None;
+                    }
+                    pairs.push((key, self.value()?));
+                    self.skip_whitespace();
+                    match self.chars.next()? {
+                        ',' => continue,
+                        '}' => return Some(Value::Object(pairs)),
+                        _ => return None,
+                    }
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn scalar(&mut self) -> Option<Value> {
+        match *self.chars.peek()? {
+            'n' => self.expect("null").map(|_| Value::Null),
+            't' => self.expect("true").map(|_| Value::Bool(true)),
+            'f' => self.expect("false").map(|_| Value::Bool(false)),
+            '"' => self.string().map(Value::String),
+            _ => {
+                let mut number = String::new();
+                while let Some(&c) = self.chars.peek() {
+                    if !(c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')) {
+                        break;
+                    }
+                    number.push(c);
+                    self.chars.next();
+                }
+                number.parse().ok().map(Value::Number)
+            }
+        }
+    }
+
+    fn hex4(&mut self) -> Option<u32> {
+        let digits: String = (0..4).map(|_| self.chars.next()).collect::<Option<_>>()?;
+        u32::from_str_radix(&digits, 16).ok()
+    }
+
+    fn string(&mut self) -> Option<String> {
+        if self.chars.next()? != '"' {
+            return None;
+        }
+        let mut s = String::new();
+        loop {
+            match self.chars.next()? {
+                '"' => return Some(s),
+                '\\' => match self.chars.next()? {
+                    'n' => s.push('\n'),
+                    'r' => s.push('\r'),
+                    't' => s.push('\t'),
+                    'b' => s.push('\u{8}'),
+                    'f' => s.push('\u{c}'),
+                    'u' => {
+                        let mut code = self.hex4()?;
+                        // Characters outside the BMP come as a surrogate pair
+                        if (0xD800..0xDC00).contains(&code) {
+                            self.expect("\\u")?;
+                            let low = self.hex4()?;
+                            code = 0x10000 + ((code - 0xD800) << 10) + (low.checked_sub(0xDC00)? & 0x3FF);
+                        }
+                        s.push(char::from_u32(code)?);
+                    }
+                    c => s.push(c),
+                },
+                c => s.push(c),
+            }
+        }
+    }
+}
+
+pub fn parse(text: &str) -> Option<Value> {
+    let mut parser = Parser { chars: text.chars().peekable(), depth: 0 };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    match parser.chars.next() {
+        Some(_) => None,
+        None => Some(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_values() {
+        let cases = [
+            ("null", Value::Null),
+            (" true ", Value::Bool(true)),
+            ("-12.5e1", Value::Number(-125.0)),
+            (r#""a\"b\\c\né😀""#, Value::from("a\"b\\c\né😀")),
+            ("[]", Value::Array(vec![])),
+            ("[1, [2], {}]", Value::Array(vec![1.into(), Value::Array(vec![2.into()]), Value::Object(vec![])])),
+            (
+                r#"{"b": null, "a": ["x"]}"#,
+                Value::object(vec![("b", Value::Null), ("a", vec!["x"].into())]),
+            ),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(parse(text), Some(expected), "{}", text);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        for text in ["", "nul", "[1,", "[1 2]", r#"{"a" 1}"#, r#"{a: 1}"#, r#""open"#, "1 2", r#""\ud83d""#, "{}}"] {
+            assert_eq!(parse(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn limits_nesting() {
+        let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(parse(&nested(MAX_DEPTH)).is_some());
+        assert_eq!(parse(&nested(MAX_DEPTH + 1)), None);
+        let deep_objects = r#"{"a":"#.repeat(MAX_DEPTH + 1) + "1" + &"}".repeat(MAX_DEPTH + 1);
+        assert_eq!(parse(&deep_objects), None);
+        // Far past the limit, as a hostile daemon request might be: refused,
+        // not a stack overflow
+        assert_eq!(parse(&"[".repeat(1_000_000)), None);
+    }
+
+    #[test]
+    fn writes_what_it_reads() {
+        let value = Value::object(vec![
+            ("text", "tab\there \"quoted\" \u{1}".into()),
+            ("number", 42.into()),
+            ("fraction", Value::Number(0.5)),
+            ("none", Option::<i64>::None.into()),
+            ("list", vec!["a", "b"].into()),
+            ("flag", false.into()),
+        ]);
+        let line = value.to_string();
+        assert!(!line.contains('\n'));
+        assert_eq!(line, r#"{"text":"tab\there \"quoted\" \u0001","number":42,"fraction":0.5,"none":null,"list":["a","b"],"flag":false}"#);
+        assert_eq!(parse(&line), Some(value));
+        assert_eq!(Value::Number(f64::NAN).to_string(), "null");
+    }
+}
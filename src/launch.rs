@@ -0,0 +1,1117 @@
+// src/launch.rs - Launching from other programs: describe a popup with a
+// LaunchRequest, open it with a Launcher and follow it through the Session
+// that comes back. Popups run on their own either way; a Session only reads
+// the runtime registry and the files the popup has open.
+use std::collections::VecDeque;
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use arboard::Clipboard;
+
+use crate::config::{
+    history_path, learned_pause, learned_pause_path, remembered_geometry, rund_exe, AppGeometry, Config, PauseBehavior,
+    UnknownPause,
+};
+use crate::hooks::calculate_file_hash;
+use crate::placement::{self, Placement};
+use crate::terminal::{self, backend_name};
+use crate::wrapper::{PauseLearning, PausePlan, WindowTracking, WrapperHooks, EXIT_CODE_ARG};
+use crate::{history, hooks, log, notify, paths, runtime, screen, shell};
+
+// How often a Session looks at the registry and its files
+const POLL: Duration = Duration::from_millis(250);
+
+/// What to run and how, the way rund's command line says it:
+///
+/// ```no_run
+/// use rund::{LaunchRequest, Launcher, PauseBehavior};
+///
+/// let launcher = Launcher::from_config_file()?;
+/// let request = LaunchRequest::new("cargo test").title("tests").pause(PauseBehavior::OnError);
+/// let session = launcher.launch(&request)?;
+/// println!("exit code {:?}", session.wait());
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct LaunchRequest {
+    app: String,
+    args: Vec<String>,
+    options: RunOptions,
+}
+
+impl LaunchRequest {
+    /// `app` is a program or a whole command line, e.g. "cargo build"; an
+    /// empty one runs the config's default_app
+    pub fn new(app: impl Into<String>) -> LaunchRequest {
+        LaunchRequest { app: app.into(), args: Vec::new(), options: RunOptions::default() }
+    }
+
+    // A launch parsed from rund's own command line
+    pub(crate) fn from_options(app: String, args: Vec<String>, options: RunOptions) -> LaunchRequest {
+        LaunchRequest { app, args, options }
+    }
+
+    // Also kept as command line options, so the history can show the launch
+    // and `rund again` repeat it
+    fn option(mut self, args: &[&str]) -> LaunchRequest {
+        self.options.args.extend(args.iter().map(|arg| arg.to_string()));
+        self
+    }
+
+    /// An argument for the app. Ones that name files are made absolute, and
+    /// file:line:col ones opened there in editors.
+    pub fn arg(mut self, arg: impl Into<String>) -> LaunchRequest {
+        self.args.push(arg.into());
+        self
+    }
+
+    pub fn args<I, S>(mut self, args: I) -> LaunchRequest
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn title(mut self, title: &str) -> LaunchRequest {
+        self.options.title = Some(title.to_string());
+        self.option(&["--title", title])
+    }
+
+    /// A terminal by name (alacritty, kitty, tmux, wt, ...) instead of the
+    /// first one found
+    pub fn terminal(mut self, name: &str) -> LaunchRequest {
+        self.options.terminal = Some(name.to_string());
+        self.option(&["--terminal", name])
+    }
+
+    /// Where the command starts (default: the current directory)
+    pub fn cwd(mut self, dir: impl AsRef<Path>) -> LaunchRequest {
+        let dir = dir.as_ref();
+        self.options.cwd = Some(dir.to_path_buf());
+        self.option(&["--cwd", &dir.display().to_string()])
+    }
+
+    /// Window size in pixels
+    pub fn size(mut self, width: u32, height: u32) -> LaunchRequest {
+        let size = format!("{}x{}", width, height);
+        self.options.geometry.parse_size(&size);
+        self.option(&["--size", &size])
+    }
+
+    /// Window size in character cells
+    pub fn cells(mut self, cols: u32, rows: u32) -> LaunchRequest {
+        let (cols, rows) = (cols.max(1), rows.max(1));
+        self.options.geometry.width = None;
+        self.options.geometry.height = None;
+        self.options.geometry.cols = Some(cols);
+        self.options.geometry.rows = Some(rows);
+        self.option(&["--geometry", &format!("{}x{}", cols, rows)])
+    }
+
+    /// Window position in pixels
+    pub fn position(mut self, x: i32, y: i32) -> LaunchRequest {
+        let pos = format!("{},{}", x, y);
+        self.options.geometry.parse_pos(&pos);
+        self.option(&["--pos", &pos])
+    }
+
+    pub fn pause(mut self, pause: PauseBehavior) -> LaunchRequest {
+        self.options.pause = Some(pause);
+        let mode = match pause {
+            PauseBehavior::Never => "never",
+            PauseBehavior::Always => "always",
+            PauseBehavior::Auto => "auto",
+            PauseBehavior::OnError => "on_error",
+            PauseBehavior::OnErrorOrFast => "on_error_or_fast",
+        };
+        self.option(&[&format!("--pause={}", mode)])
+    }
+
+    /// The file the app is opened on, appended to its command line; relative
+    /// to the current directory
+    pub fn output_file(mut self, path: impl AsRef<Path>) -> LaunchRequest {
+        let path = path.as_ref();
+        self.options.output_file = Some(path.to_path_buf());
+        self.option(&["--output", &path.display().to_string()])
+    }
+
+    /// Write the clipboard to the output file (or a temporary one) first
+    pub fn clipboard(mut self) -> LaunchRequest {
+        self.options.use_clipboard = true;
+        self.option(&["--clipboard"])
+    }
+
+    /// Where the output file is backed up if the app changes it
+    pub fn backup_dir(mut self, dir: impl AsRef<Path>) -> LaunchRequest {
+        let dir = dir.as_ref();
+        self.options.backup_dir = Some(dir.to_path_buf());
+        self.option(&["--backup", &dir.display().to_string()])
+    }
+
+    /// Re-run the command in the popup when files matching `glob` change
+    pub fn watch(mut self, glob: &str) -> LaunchRequest {
+        self.options.watch.push(glob.to_string());
+        self.option(&["--watch", glob])
+    }
+
+    pub fn always_on_top(mut self) -> LaunchRequest {
+        self.options.always_on_top = true;
+        self.option(&["--top"])
+    }
+}
+
+/// Opens popups with one config
+#[derive(Debug)]
+pub struct Launcher {
+    config: Config,
+}
+
+impl Launcher {
+    pub fn new(config: Config) -> Launcher {
+        Launcher { config }
+    }
+
+    /// With the user's config file, as the rund command uses it
+    pub fn from_config_file() -> io::Result<Launcher> {
+        Config::load().map(Launcher::new)
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Open the popup. With single_instance, files may go to a running
+    /// session instead, which is then the one returned.
+    pub fn launch(&self, request: &LaunchRequest) -> io::Result<Session> {
+        let app = match (request.app.as_str(), self.config.default_app()) {
+            ("", Some(app)) => app,
+            ("", None) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "No app specified and no default_app in config",
+                ))
+            }
+            (app, _) => app,
+        };
+        let id = launch_command(app, &request.args, &self.config, &request.options)?;
+        let files = runtime::sessions()
+            .into_iter()
+            .find(|s| s.id == id)
+            .map(|s| s.files)
+            .unwrap_or_default();
+        Ok(Session { id, files })
+    }
+}
+
+/// A launched popup. Dropping it leaves the popup open.
+#[derive(Debug, Clone)]
+pub struct Session {
+    id: String,
+    files: Vec<PathBuf>,
+}
+
+impl Session {
+    /// The id `rund ps`, `rund kill` and `rund focus` know the session by
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Files the popup was opened on, absolute; the ones `events` watches
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    fn running(&self) -> Option<runtime::Session> {
+        runtime::sessions().into_iter().find(|s| s.id == self.id)
+    }
+
+    /// None while the popup is open. Once it closed, Some(exit code of the
+    /// command), the code None when the popup was killed or it is not known.
+    pub fn try_wait(&self) -> Option<Option<i64>> {
+        if let Some(code) = runtime::closed(&self.id) {
+            return Some(code);
+        }
+        if self.running().is_some() {
+            return None;
+        }
+        // Gone without a word: its wrapper died before it could write the
+        // exit file; the history may still have the exit code
+        let entries = history::load(&history_path());
+        Some(entries.into_iter().find(|entry| entry.id == self.id).and_then(|entry| entry.exit_code))
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.try_wait().is_none()
+    }
+
+    /// Block until the popup closes; the command's exit code, when known
+    pub fn wait(&self) -> Option<i64> {
+        loop {
+            if let Some(code) = self.try_wait() {
+                return code;
+            }
+            thread::sleep(POLL);
+        }
+    }
+
+    /// Changes to the session's files as they happen, then how it exited.
+    /// Blocks between events; ends after `Event::Exited`.
+    pub fn events(&self) -> Events<'_> {
+        Events {
+            session: self,
+            stamps: self.files.iter().map(|file| (file.clone(), stamp(file))).collect(),
+            pending: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    pub fn kill(&self) -> io::Result<()> {
+        runtime::kill(&self.running().ok_or_else(not_running)?)
+    }
+
+    /// Raise the popup's window
+    pub fn focus(&self) -> io::Result<()> {
+        runtime::focus(&self.running().ok_or_else(not_running)?)
+    }
+}
+
+fn not_running() -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, "The session is no longer running")
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// One of the session's files was written, created or removed
+    FileChanged(PathBuf),
+    /// The popup closed, with the command's exit code when known
+    Exited(Option<i64>),
+}
+
+/// The iterator `Session::events` returns
+pub struct Events<'a> {
+    session: &'a Session,
+    stamps: Vec<(PathBuf, Option<(SystemTime, u64)>)>,
+    pending: VecDeque<Event>,
+    done: bool,
+}
+
+// Modification time and size; None for a file that does not exist
+fn stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+impl Iterator for Events<'_> {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Event> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(event);
+            }
+            if self.done {
+                return None;
+            }
+            // Exit first and files after, so the last writes before the popup
+            // closed still come before Exited
+            let exited = self.session.try_wait();
+            for (path, last) in &mut self.stamps {
+                let now = stamp(path);
+                if now != *last {
+                    *last = now;
+                    self.pending.push_back(Event::FileChanged(path.clone()));
+                }
+            }
+            if let Some(code) = exited {
+                self.pending.push_back(Event::Exited(code));
+                self.done = true;
+            }
+            if self.pending.is_empty() {
+                thread::sleep(POLL);
+            }
+        }
+    }
+}
+
+// A launch as the command line describes it; LaunchRequest builds one
+#[derive(Debug, Default)]
+pub(crate) struct RunOptions {
+    pub(crate) always_on_top: bool,
+    pub(crate) use_clipboard: bool,
+    pub(crate) output_file: Option<PathBuf>,
+    pub(crate) backup_dir: Option<PathBuf>,
+    // One-off overrides from the command line, applied on top of config
+    pub(crate) geometry: GeometryOverride,
+    pub(crate) pause: Option<PauseBehavior>,
+    pub(crate) notify: Option<notify::Notify>,
+    pub(crate) title: Option<String>,
+    pub(crate) terminal: Option<String>,
+    pub(crate) shell: Option<shell::Shell>,
+    pub(crate) cwd: Option<PathBuf>,
+    // Open a location picked from compiler/grep output on stdin
+    pub(crate) from_quickfix: bool,
+    // Take the first quickfix location instead of asking
+    pub(crate) first: bool,
+    // Re-run the command in the popup when files matching these change
+    pub(crate) watch: Vec<String>,
+    // Layout file and window name when started by `rund layout up`
+    pub(crate) layout: Option<(String, String)>,
+    // -v count among the options: more detail in the log
+    pub(crate) verbose: u8,
+    // The options as given, kept in the history for `rund again`
+    pub(crate) args: Vec<String>,
+    // Set by the daemon for a client's launch: the client's directory and
+    // environment stand in for the daemon's own
+    pub(crate) client_dir: Option<PathBuf>,
+    pub(crate) client_env: Option<Vec<(String, String)>>,
+}
+
+impl RunOptions {
+    // Where relative paths on the command line are resolved from
+    pub(crate) fn current_dir(&self) -> io::Result<PathBuf> {
+        match self.client_dir {
+            Some(ref dir) => Ok(dir.clone()),
+            None => env::current_dir(),
+        }
+    }
+
+    // A variable of the environment the popup gets
+    #[cfg(target_os = "linux")]
+    pub(crate) fn var(&self, name: &str) -> Option<std::ffi::OsString> {
+        match self.client_env {
+            Some(ref vars) => vars.iter().find(|(key, _)| key == name).map(|(_, value)| value.into()),
+            None => env::var_os(name),
+        }
+    }
+
+    // A command that runs with the client's environment when there is one
+    pub(crate) fn command(&self, program: &str) -> Command {
+        let mut command = Command::new(program);
+        if let Some(ref vars) = self.client_env {
+            command.env_clear().envs(vars.iter().map(|(key, value)| (key, value)));
+        }
+        command
+    }
+}
+
+// Geometry given on the command line; unset fields keep the configured value
+#[derive(Debug, Default)]
+pub(crate) struct GeometryOverride {
+    pub(crate) width: Option<u32>,
+    pub(crate) height: Option<u32>,
+    pub(crate) cols: Option<u32>,
+    pub(crate) rows: Option<u32>,
+    pub(crate) x: Option<i32>,
+    pub(crate) y: Option<i32>,
+}
+
+impl GeometryOverride {
+    // `--geometry COLSxROWS[+X+Y]`, size in cells like X11 geometry strings
+    pub(crate) fn parse_cells(value: &str) -> Option<Self> {
+        let split = value.find(['+', '-']).unwrap_or(value.len());
+        let (size, offset) = value.split_at(split);
+        let (cols, rows) = size.split_once('x')?;
+        let mut geometry = GeometryOverride {
+            cols: Some(cols.trim().parse::<u32>().ok()?.max(1)),
+            rows: Some(rows.trim().parse::<u32>().ok()?.max(1)),
+            ..Default::default()
+        };
+
+        if !offset.is_empty() {
+            let second = offset[1..].find(['+', '-'])? + 1;
+            let (x, y) = offset.split_at(second);
+            geometry.x = Some(x.trim_start_matches('+').parse().ok()?);
+            geometry.y = Some(y.trim_start_matches('+').parse().ok()?);
+        }
+
+        Some(geometry)
+    }
+
+    // `--size WIDTHxHEIGHT` in pixels
+    pub(crate) fn parse_size(&mut self, value: &str) -> Option<()> {
+        let (width, height) = value.split_once('x')?;
+        self.width = Some(width.trim().parse().ok()?);
+        self.height = Some(height.trim().parse().ok()?);
+        self.cols = None;
+        self.rows = None;
+        Some(())
+    }
+
+    // `--pos X,Y` in pixels
+    pub(crate) fn parse_pos(&mut self, value: &str) -> Option<()> {
+        let (x, y) = value.split_once(',')?;
+        self.x = Some(x.trim().parse().ok()?);
+        self.y = Some(y.trim().parse().ok()?);
+        Some(())
+    }
+
+    // Explicit pixels win over the configured percentages and anchor
+    pub(crate) fn apply(&self, mut geom: AppGeometry) -> AppGeometry {
+        if let Some(width) = self.width {
+            geom.width = width;
+            geom.cols = None;
+            geom.relative.width_pct = None;
+        }
+        if let Some(height) = self.height {
+            geom.height = height;
+            geom.rows = None;
+            geom.relative.height_pct = None;
+        }
+        if let Some(cols) = self.cols {
+            geom.cols = Some(cols);
+            geom.relative.width_pct = None;
+        }
+        if let Some(rows) = self.rows {
+            geom.rows = Some(rows);
+            geom.relative.height_pct = None;
+        }
+        // An explicit position only makes sense if the terminal honors it
+        if let Some(x) = self.x {
+            geom.x = x;
+            geom.relative.x_pct = None;
+            geom.relative.anchor = None;
+            geom.auto_position = false;
+        }
+        if let Some(y) = self.y {
+            geom.y = y;
+            geom.relative.y_pct = None;
+            geom.relative.anchor = None;
+            geom.auto_position = false;
+        }
+        geom
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum FileFit {
+    Fits,
+    Overflows,
+    Binary,
+}
+
+// Stream the file and stop as soon as the answer is known: a NUL byte in the
+// first 8 KiB marks it binary (same heuristic as git), and counting stops
+// once there are more lines than the window has rows.
+pub(crate) fn measure_file(path: &PathBuf, rows: u32) -> io::Result<FileFit> {
+    const SNIFF_LEN: usize = 8192;
+
+    let mut file = fs::File::open(path)?;
+    let mut buffer = [0u8; SNIFF_LEN];
+    let mut offset = 0usize;
+    let mut lines = 0u32;
+    let mut partial_line = false;
+
+    loop {
+        let n = file.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        let chunk = &buffer[..n];
+
+        if offset < SNIFF_LEN && chunk[..n.min(SNIFF_LEN - offset)].contains(&0) {
+            return Ok(FileFit::Binary);
+        }
+        offset += n;
+
+        lines += chunk.iter().filter(|&&b| b == b'\n').count() as u32;
+        partial_line = chunk[n - 1] != b'\n';
+        if lines >= rows {
+            return Ok(FileFit::Overflows);
+        }
+    }
+
+    if partial_line {
+        lines += 1;
+    }
+    Ok(if lines < rows { FileFit::Fits } else { FileFit::Overflows })
+}
+
+// Executable name of an app command without directory or extension, as
+// sessions record it
+pub(crate) fn exe_stem(app: &str) -> String {
+    let first_word = app.split_whitespace().next().unwrap_or("");
+    std::path::Path::new(first_word)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or(first_word)
+        .to_lowercase()
+}
+
+// Escape a path for an Ex command line such as `:edit`
+pub(crate) fn ex_escape(path: &std::path::Path) -> String {
+    path.display().to_string().chars().fold(String::new(), |mut escaped, c| {
+        if matches!(c, ' ' | '%' | '#' | '|' | '"') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+        escaped
+    })
+}
+
+// single_instance: instead of a second window, send the files to a running
+// nvim over its server, or raise the session that already has one of them
+// open. Returns that session's id, or None when there is no such session
+// and the app should start.
+pub(crate) fn reuse_session(app: &str, targets: &[paths::Target], options: &RunOptions) -> io::Result<Option<String>> {
+    if targets.is_empty() {
+        return Ok(None);
+    }
+    let sessions = runtime::sessions();
+    let exe = exe_stem(app);
+    let program = app.split_whitespace().next().unwrap_or("nvim");
+
+    if let Some(session) = sessions.iter().rev().find(|s| s.app == exe && s.server.is_some()) {
+        let server = session.server.as_deref().unwrap_or_default();
+        for target in targets {
+            let mut remote = options.command(program);
+            remote.args(["--server", server]);
+            match target.line {
+                Some(line) => remote.args([
+                    "--remote-send",
+                    &format!(
+                        "<C-\\><C-N>:edit {}<CR>:call cursor({}, {})<CR>",
+                        ex_escape(&target.path),
+                        line,
+                        target.col.unwrap_or(1)
+                    ),
+                ]),
+                None => remote.arg("--remote").arg(&target.path),
+            };
+            if !remote.status()?.success() {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    format!("Could not send {} to {} at {}", target.path.display(), exe, server),
+                ));
+            }
+        }
+        // The files are open either way; a window that cannot be raised is no failure
+        runtime::focus(session).ok();
+        return Ok(Some(session.id.clone()));
+    }
+
+    let open = sessions
+        .iter()
+        .find(|s| s.app == exe && targets.iter().any(|t| s.files.contains(&t.path)));
+    match open {
+        Some(session) => {
+            runtime::focus(session).map_err(|e| {
+                io::Error::new(e.kind(), format!("Already open in session '{}' ({})", session.title, e))
+            })?;
+            Ok(Some(session.id.clone()))
+        }
+        None => Ok(None),
+    }
+}
+
+// Open the popup; returns the id of its session, or of the running session
+// that single_instance handed the files to
+pub(crate) fn run_in_terminal(app: &str, targets: &[paths::Target], config: &Config, options: &RunOptions) -> io::Result<String> {
+    log::info!("launching: {}", app);
+    let single_instance = config.single_instance(app);
+    if single_instance {
+        let mut wanted = targets.to_vec();
+        if let Some(ref output) = options.output_file {
+            let path = options.current_dir()?.join(output);
+            wanted.push(paths::Target { path: dunce::canonicalize(&path).unwrap_or(path), line: None, col: None });
+        }
+        if let Some(id) = reuse_session(app, &wanted, options)? {
+            log::info!("single_instance: handed to running session {}", id);
+            return Ok(id);
+        }
+    }
+
+    // Made absolute here: the backup is taken when the popup exits, from
+    // wherever the wrapper runs
+    let backup_dir = options.current_dir()?.join(options.backup_dir.as_ref().unwrap_or(&config.backup_dir));
+
+    // Start in rund's own directory (the client's, for the daemon) unless
+    // told otherwise, rather than wherever the terminal emulator would pick
+    let environment = config.environment(app);
+    let cwd = environment.working_dir(options.cwd.as_deref(), options.current_dir()?)?;
+    log::debug!("working directory: {}", cwd.display());
+
+    let (file_path, initial_hash) = if options.use_clipboard || options.output_file.is_some() {
+        let file_path = if let Some(ref output) = options.output_file {
+            // Relative to where rund was started, whatever the popup's cwd
+            let path = options.current_dir()?.join(output);
+
+            if options.use_clipboard {
+                let mut clipboard = Clipboard::new().map_err(|e| {
+                    io::Error::new(io::ErrorKind::Other, format!("Clipboard error: {}", e))
+                })?;
+
+                let content = clipboard.get_text().map_err(|e| {
+                    io::Error::new(io::ErrorKind::Other, format!("Clipboard error: {}", e))
+                })?;
+
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+
+                fs::write(&path, content)?;
+            }
+
+            path
+        } else {
+            let mut clipboard = Clipboard::new().map_err(|e| {
+                io::Error::new(io::ErrorKind::Other, format!("Clipboard error: {}", e))
+            })?;
+
+            let content = clipboard.get_text().map_err(|e| {
+                io::Error::new(io::ErrorKind::Other, format!("Clipboard error: {}", e))
+            })?;
+
+            let temp_dir = env::temp_dir();
+            let timestamp = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs();
+            let temp_file = temp_dir.join(format!("rund_clipboard_{}.txt", timestamp));
+
+            fs::write(&temp_file, content)?;
+            temp_file
+        };
+
+        let initial_hash = if file_path.exists() {
+            calculate_file_hash(&file_path)?
+        } else {
+            String::new()
+        };
+
+        (Some(file_path), initial_hash)
+    } else {
+        (None, String::new())
+    };
+
+    // The file hooks are about: the -c/-o file, or else the first file argument
+    let app_hooks = config.hooks(app);
+    let hook_file = file_path.clone().or_else(|| targets.first().map(|t| t.path.clone()));
+    for command in &app_hooks.pre_launch {
+        let file = hook_file.as_ref().map(|f| f.display().to_string()).unwrap_or_default();
+        let code = hooks::run(command, &cwd, &[("RUND_FILE", file)], options.client_env.as_deref())?;
+        log::debug!("pre_launch '{}' exited with code {}", command, code);
+        if code != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("pre_launch hook '{}' exited with code {}", command, code),
+            ));
+        }
+    }
+
+    // Detect app type dari command name - GUNAKAN CONFIG!
+    let app_lower = app.to_lowercase();
+    let app_first_word = app_lower.split_whitespace().next().unwrap_or("");
+    
+    // Check against config lists
+    let is_editor = config.is_editor(app);
+    
+    let is_viewer = config.viewer_apps.iter()
+        .any(|v| app_first_word.contains(&v.to_lowercase()) || app_first_word == v.to_lowercase());
+    
+    let is_always_pause = config.always_pause_apps.iter()
+        .any(|a| app_first_word.contains(&a.to_lowercase()) || app_first_word == a.to_lowercase());
+    
+    // Executable name without its directory, as the learned pause state keys it
+    let exe_name = PathBuf::from(app_first_word)
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or(app_first_word)
+        .to_string();
+    let is_unknown = !is_editor && !is_viewer && !is_always_pause;
+
+    // Special case: 'type' command needs '| more' for large files!
+    let is_type_command = app_first_word == "type";
+
+    // Get geometry for this specific app (with fallback to default), or
+    // where its window was last left when remember_geometry is on
+    let remember = config.remember_geometry(app);
+    let remembered = remembered_geometry(app_first_word).filter(|_| remember);
+    let geom = match remembered {
+        Some(rect) => config.get_geometry(app).remembered(rect),
+        None => config.get_geometry(app),
+    };
+    let geom = options.geometry.apply(geom);
+    // Only ask the display server when percentages or an anchor need it
+    let backend = backend_name(config, options);
+    let cell = config.cell_size(&backend);
+    // A remembered window goes back exactly where it was, and a layout's
+    // windows where the layout puts them
+    let placing = config.placement != Placement::None
+        && !geom.auto_position
+        && remembered.is_none()
+        && options.layout.is_none();
+    // Only ask the display server when percentages, an anchor or placement need it
+    let screen = if geom.is_relative() || placing {
+        screen::select(geom.relative.monitor.as_ref())
+    } else {
+        None
+    };
+    let mut geom = geom.resolve(screen, cell);
+    log::debug!(
+        "backend {}, cell {}x{}, screen {:?}, geometry {:?}{}",
+        backend,
+        cell.width,
+        cell.height,
+        screen,
+        geom,
+        if remembered.is_some() { " (remembered)" } else { "" }
+    );
+
+    // Spread out popups that are open at the same time
+    let sessions = runtime::sessions();
+    if placing {
+        let (width, height) = geom.pixels(cell);
+        let wanted = screen::Rect { x: geom.x, y: geom.y, width, height };
+        // Without a known screen, assume a common full-HD one
+        let area = screen.unwrap_or(screen::Rect { x: 0, y: 0, width: 1920, height: 1080 });
+        let open: Vec<screen::Rect> = sessions.iter().filter(|s| s.positioned).map(|s| s.rect).collect();
+        let rect = placement::place(config.placement, wanted, area, &open, &config.placement_options);
+
+        log::debug!("placement {:?} moved the window to {:?}", config.placement, rect);
+        geom.x = rect.x;
+        geom.y = rect.y;
+        if (rect.width, rect.height) != (width, height) {
+            geom.width = rect.width;
+            geom.height = rect.height;
+            geom.cols = None;
+            geom.rows = None;
+        }
+    }
+
+    // Register the popup as a session for rund ps, kill and focus
+    let (width, height) = geom.pixels(cell);
+    let mut session_files: Vec<PathBuf> = targets.iter().map(|t| t.path.clone()).collect();
+    session_files.extend(file_path.clone());
+    let id = runtime::new_id();
+    // A single-instance nvim listens where later launches can reach it
+    let server = if single_instance && exe_stem(app) == "nvim" {
+        Some(if cfg!(target_os = "windows") {
+            format!("\\\\.\\pipe\\rund-nvim-{}", id)
+        } else {
+            runtime::runtime_dir()?.join(format!("nvim-{}.sock", id)).display().to_string()
+        })
+    } else {
+        None
+    };
+    let session = runtime::Session {
+        id: id.clone(),
+        rect: screen::Rect { x: geom.x, y: geom.y, width, height },
+        positioned: !geom.auto_position,
+        backend: backend.clone(),
+        app: exe_stem(app),
+        title: options.title.clone().unwrap_or_else(|| "rund".to_string()),
+        files: session_files,
+        server,
+        layout: options.layout.clone(),
+        created: runtime::now_secs(),
+        ..runtime::Session::default()
+    };
+    #[cfg(not(target_os = "windows"))]
+    let window_var = match backend.as_str() {
+        "tmux" => Some("TMUX_PANE"),
+        _ if cfg!(target_os = "linux") => Some("WINDOWID"),
+        _ => None,
+    };
+    log::debug!("session {}", session.id);
+    let mut hooks = WrapperHooks {
+        env: environment,
+        tracking: Some(WindowTracking {
+            id: session.id.clone(),
+            // Fails, and so does the launch, when the runtime directory is not safe to use
+            file: runtime::windows_file()?,
+            exit_file: runtime::exit_file(&session.id)?,
+            #[cfg(not(target_os = "windows"))]
+            window_var,
+        }),
+        on_exit: Vec::new(),
+    };
+    // The wrapper calls back into rund before the window goes away to record
+    // where it ended up
+    if remember {
+        if let Ok(exe) = rund_exe() {
+            hooks.on_exit.push(vec![
+                exe.display().to_string(),
+                "--record-geometry".to_string(),
+                app_first_word.to_string(),
+            ]);
+        }
+    }
+    // The launch under the session's id, recorded once the command is ready
+    let history_entry = config.history.then(|| {
+        let position = |value: i32| Some(value).filter(|_| !geom.auto_position);
+        history::Entry {
+            id: session.id.clone(),
+            started: session.created,
+            cwd: cwd.display().to_string(),
+            command: app.to_string(),
+            options: options.args.clone(),
+            profile: config.profile(app),
+            geometry: Some(history::Geometry { x: position(geom.x), y: position(geom.y), width, height }),
+            files: session.files.iter().map(|f| f.display().to_string()).collect(),
+            ..history::Entry::default()
+        }
+    });
+
+    // What rund does once the popup exits: record the exit code, notify,
+    // back up a changed -c/-o file and run post_exit/on_change. The wrapper calls back
+    // into rund for it as it exits.
+    let completion = hooks::Completion {
+        id: session.id.clone(),
+        cwd: cwd.clone(),
+        command: app.to_string(),
+        started: runtime::now_secs(),
+        notify: options.notify.unwrap_or_else(|| config.notify(app)),
+        hash: match (&file_path, &hook_file) {
+            (Some(_), _) => initial_hash.clone(),
+            (None, Some(file)) if !app_hooks.on_change.is_empty() => calculate_file_hash(file).unwrap_or_default(),
+            _ => String::new(),
+        },
+        file: hook_file,
+        backup_dir: file_path.as_ref().map(|_| backup_dir),
+        post_exit: app_hooks.post_exit,
+        on_change: app_hooks.on_change,
+        history: history_entry.is_some(),
+    };
+    if completion.is_needed() {
+        if let Ok(exe) = rund_exe() {
+            hooks.on_exit.push(vec![
+                exe.display().to_string(),
+                "--record-exit".to_string(),
+                completion.id.clone(),
+                EXIT_CODE_ARG.to_string(),
+            ]);
+        }
+    }
+
+    // A watching popup runs until it is closed; there is no end to pause at
+    let pause_behavior = match options.pause {
+        Some(behavior) => behavior,
+        None if !options.watch.is_empty() => PauseBehavior::Never,
+        None => config.pause_behavior,
+    };
+
+    // Viewers are judged by the file they show: the -c/-o file, or else the
+    // last argument that names an existing file (in a command line given as
+    // one string, the last word that does)
+    let viewer_target = if is_viewer {
+        file_path.clone().or_else(|| targets.iter().rev().map(|t| t.path.clone()).find(|p| p.is_file())).or_else(|| {
+            app.split_whitespace()
+                .skip(1)
+                .map(PathBuf::from)
+                .filter(|p| p.is_file())
+                .last()
+        })
+    } else {
+        None
+    };
+
+    let viewer_fit = match viewer_target {
+        Some(ref path) if path.exists() => measure_file(path, config.viewer_pause_rows(&geom, cell)).ok(),
+        _ => None,
+    };
+
+    // The shell the command is written for
+    let configured_shell = config.shell(app, options.shell.as_ref());
+    let shell = configured_shell.clone().unwrap_or_default();
+
+    // Binary files would only garble the terminal: hand them to the hex viewer
+    // or refuse before a window is opened
+    let mut hex_app = None;
+    if viewer_fit == Some(FileFit::Binary) {
+        let path = viewer_target.as_ref().unwrap();
+        match config.hex_viewer {
+            Some(ref hex) if file_path.is_some() => hex_app = Some(hex.clone()),
+            Some(ref hex) => hex_app = Some(format!("{} {}", hex, shell.quote(&path.display().to_string()))),
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "{} looks like a binary file; set hex_viewer in config to view it",
+                        path.display()
+                    ),
+                ));
+            }
+        }
+    }
+
+    // SMART PAUSE DETECTION:
+    // - Editors: NEVER need pause (mereka interactive)
+    // - Viewers: Need pause ONLY for files that fit in the window
+    // - Binary files shown in the hex viewer: ALWAYS pause
+    // - Always pause apps: ALWAYS pause
+    // - Unknown: Pause by default (safe)
+    let needs_pause = if is_editor {
+        // Editors never need pause - they're interactive!
+        false
+    } else if is_always_pause || hex_app.is_some() {
+        // Apps explicitly marked to always pause
+        true
+    } else if is_viewer {
+        // Small file needs pause to see output
+        viewer_fit == Some(FileFit::Fits)
+    } else {
+        // Unknown commands - pause by default for safety, unless configured
+        // otherwise or learned from how quickly the prompt gets dismissed
+        match config.unknown_apps_pause {
+            UnknownPause::Always => true,
+            UnknownPause::Never => false,
+            UnknownPause::Learn => learned_pause(&exe_name, config.learn_runs),
+        }
+    };
+
+    // Override with config if explicitly set
+    let mut pause = PausePlan {
+        behavior: match pause_behavior {
+            PauseBehavior::Auto if needs_pause => PauseBehavior::Always, // Smart detection!
+            PauseBehavior::Auto => PauseBehavior::Never,
+            explicit => explicit,
+        },
+        fast_exit_secs: config.fast_exit_secs,
+        timeout: config.pause_timeout(app),
+        learn: None,
+    };
+    // Only learn from prompts that auto mode added for an unknown command, and
+    // only for names that are safe to embed in the generated script
+    if pause_behavior == PauseBehavior::Auto
+        && config.unknown_apps_pause == UnknownPause::Learn
+        && is_unknown
+        && pause.behavior == PauseBehavior::Always
+        && !exe_name.is_empty()
+        && exe_name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+    {
+        pause.learn = Some(PauseLearning {
+            exe: exe_name.clone(),
+            state_file: learned_pause_path(),
+        });
+    }
+    
+    let class = if is_editor {
+        "editor"
+    } else if is_always_pause {
+        "always-pause app"
+    } else if is_viewer {
+        "viewer"
+    } else {
+        "unknown app"
+    };
+    log::info!(
+        "{}: {}, pause {:?} (configured {:?}, file fit {:?}){}",
+        exe_name,
+        class,
+        pause.behavior,
+        pause_behavior,
+        viewer_fit,
+        if pause.learn.is_some() { ", learning" } else { "" }
+    );
+
+    // Modify app command for 'type' with large files
+    let final_app = if let Some(hex) = hex_app {
+        hex
+    } else if is_type_command && !needs_pause {
+        // Large file with type command - add '| more' for paging
+        format!("{} | more", app)
+    } else {
+        app.to_string()
+    };
+
+    // The command line in the chosen shell's syntax; shells the wrapper cannot
+    // run in are called from it instead
+    let final_app = match (&session.server, final_app.split_once(' ')) {
+        (Some(server), Some((exe, args))) => format!("{} --listen {} {}", exe, shell.quote(server), args),
+        (Some(server), None) => format!("{} --listen {}", final_app, shell.quote(server)),
+        (None, _) => final_app,
+    };
+    let command = match file_path {
+        Some(ref path) => format!("{} {}", final_app, shell.quote(&path.display().to_string())),
+        None => final_app,
+    };
+    let command = shell.command(&command);
+
+    // Watch mode: rund runs in the popup and starts the command over on
+    // changes, the way the wrapper would have run it
+    let command = if options.watch.is_empty() {
+        command
+    } else {
+        let host = shell.host();
+        let mut words = vec![rund_exe()?.display().to_string(), "--watch-loop".to_string()];
+        words.extend(options.watch.iter().cloned());
+        words.push("--".to_string());
+        words.extend(host.argv(&command));
+        words.iter().map(|word| host.quote(word)).collect::<Vec<_>>().join(" ")
+    };
+
+    log::debug!("command: {}", command);
+
+    // Only now is the popup registered as a session (for rund ps, kill and
+    // focus), added to the history and left its exit work; a terminal that
+    // does not start takes that back with forget_launch
+    if let Err(e) = runtime::register(&session) {
+        log::debug!("session not registered: {}", e);
+    }
+    if let Some(ref entry) = history_entry {
+        history::record_launch(&history_path(), entry).ok();
+    }
+    if completion.is_needed() {
+        completion.save().ok();
+    }
+
+    let popup = terminal::Popup {
+        command: &command,
+        pause: &pause,
+        hooks: &hooks,
+        geom: &geom,
+        cwd: &cwd,
+        #[cfg(not(target_os = "windows"))]
+        shell: configured_shell.as_ref(),
+    };
+    terminal::open(&popup, config, options).map_err(|e| forget_launch(&id, e))?;
+    Ok(id)
+}
+
+// Take back what was recorded for a launch whose terminal did not start: the
+// session is closed, and it is left out of the history. Returns `error`.
+pub(crate) fn forget_launch(id: &str, error: io::Error) -> io::Error {
+    log::debug!("forgetting session {}", id);
+    runtime::record_close(id).ok();
+    history::record_failed(&history_path(), id).ok();
+    hooks::Completion::discard(id);
+    error
+}
+
+// Arguments that name a file become absolute, since the popup may start in
+// another directory; file:line:col ones use the app's goto_line template.
+// Other arguments are quoted for the shell, so they reach the app as given.
+// Also returns the target, for the session registry.
+pub(crate) fn rewrite_arg(arg: &str, app: &str, config: &Config, shell: &shell::Shell, cwd: Option<&Path>) -> (String, Option<paths::Target>) {
+    let cwd = match cwd {
+        Some(cwd) => cwd,
+        None => return (shell.arg(arg), None),
+    };
+    match paths::parse_target(arg, cwd, config.is_editor(app)) {
+        Some(target) => (target.render(config.goto_template(app).as_deref(), shell), Some(target)),
+        None => (shell.arg(arg), None),
+    }
+}
+
+// Run an app with its arguments, file arguments rewritten for the popup;
+// returns the id of the session it runs in
+pub(crate) fn launch_command(app: &str, app_args: &[String], config: &Config, options: &RunOptions) -> io::Result<String> {
+    let shell = config.shell(app, options.shell.as_ref()).unwrap_or_default();
+    let cwd = options.current_dir().ok();
+    let (app_args, targets): (Vec<String>, Vec<Option<paths::Target>>) =
+        app_args.iter().map(|arg| rewrite_arg(arg, app, config, &shell, cwd.as_deref())).unzip();
+    let targets: Vec<paths::Target> = targets.into_iter().flatten().collect();
+    let app_command = if app_args.is_empty() {
+        app.to_string()
+    } else {
+        format!("{} {}", app, app_args.join(" "))
+    };
+    run_in_terminal(&app_command, &targets, config, options)
+}
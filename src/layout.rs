@@ -0,0 +1,293 @@
+// src/layout.rs - `rund layout up|down FILE`: popups that are started together
+// from one file and closed together. The file is read like config.toml:
+//
+//   grid = "3x2"                 # columns x rows the monitor is divided into
+//   monitor = "primary"
+//
+//   [logs]                       # one section per popup, started in order
+//   command = "tail -f app.log"
+//   cell = "0,0"                 # column,row in the grid; span = "1x2" for more
+//
+//   [editor]
+//   command = "nvim src/main.rs"
+//   cwd = "~/projects/app"       # default: the layout file's directory
+//   geometry = "120x40+100+50"   # or size = "1000x700" and pos = "50,50"
+//   terminal = "kitty"
+//   delay = "500ms"              # wait before starting this one
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use crate::environment::expand_home;
+use crate::config::{get_config_path, Config};
+use crate::launch::launch_command;
+use crate::{cli, runtime, screen};
+
+struct Window {
+    name: String,
+    command: Option<String>,
+    cwd: Option<PathBuf>,
+    delay: Duration,
+    // Column and row in the grid, and how many of each it spans
+    cell: Option<(u32, u32)>,
+    span: (u32, u32),
+    // rund options for the launch: --terminal, --geometry, --title, ...
+    options: Vec<String>,
+}
+
+struct Layout {
+    grid: Option<(u32, u32)>,
+    monitor: Option<screen::MonitorTarget>,
+    windows: Vec<Window>,
+}
+
+// "500ms", "2s", "1.5s"; plain numbers are seconds
+fn parse_delay(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let (number, millis) = match value.strip_suffix("ms") {
+        Some(number) => (number, true),
+        None => (value.strip_suffix('s').unwrap_or(value), false),
+    };
+    let number: f64 = number.trim().parse().ok().filter(|n: &f64| *n >= 0.0 && n.is_finite())?;
+    Some(Duration::from_secs_f64(if millis { number / 1000.0 } else { number }))
+}
+
+// "3x2" (grid, span) or "0,1" (cell)
+fn parse_pair(value: &str, separator: char) -> Option<(u32, u32)> {
+    let (a, b) = value.split_once(separator)?;
+    Some((a.trim().parse().ok()?, b.trim().parse().ok()?))
+}
+
+fn parse_size(value: &str) -> Option<(u32, u32)> {
+    parse_pair(value, 'x').filter(|&(cols, rows)| cols > 0 && rows > 0)
+}
+
+fn parse(path: &Path, content: &str) -> io::Result<Layout> {
+    let mut layout = Layout { grid: None, monitor: None, windows: Vec::new() };
+    let invalid = |line: usize, message: String| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("{}:{}: {}", path.display(), line, message))
+    };
+
+    for (index, line) in content.lines().enumerate() {
+        let number = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            layout.windows.push(Window {
+                name: name.trim().to_string(),
+                command: None,
+                cwd: None,
+                delay: Duration::ZERO,
+                cell: None,
+                span: (1, 1),
+                options: Vec::new(),
+            });
+            continue;
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| invalid(number, format!("expected key = value, found '{}'", line)))?;
+        let key = key.trim();
+        let value = value.trim().trim_matches('"').trim_matches('\'');
+        let bad_value =
+            |expected: &str| invalid(number, format!("invalid {} '{}': expected {}", key, value, expected));
+
+        let window = match layout.windows.last_mut() {
+            Some(window) => window,
+            // Keys before the first section are for the whole layout
+            None => {
+                match key {
+                    "grid" => layout.grid = Some(parse_size(value).ok_or_else(|| bad_value("COLSxROWS, e.g. 3x2"))?),
+                    "monitor" => layout.monitor = screen::MonitorTarget::parse(value),
+                    _ => return Err(invalid(number, format!("unknown layout setting '{}'", key))),
+                }
+                continue;
+            }
+        };
+        match key {
+            "command" => window.command = Some(value.to_string()),
+            "cwd" => window.cwd = Some(expand_home(value)),
+            "delay" => window.delay = parse_delay(value).ok_or_else(|| bad_value("a duration like 500ms or 2s"))?,
+            "cell" => window.cell = Some(parse_pair(value, ',').ok_or_else(|| bad_value("COLUMN,ROW, e.g. 0,1"))?),
+            "span" => window.span = parse_size(value).ok_or_else(|| bad_value("COLSxROWS, e.g. 2x1"))?,
+            "terminal" | "backend" => window.options.extend(["--terminal".to_string(), value.to_string()]),
+            "geometry" | "size" | "pos" | "title" | "shell" => {
+                window.options.extend([format!("--{}", key), value.to_string()])
+            }
+            "pause" | "notify" => window.options.push(format!("--{}={}", key, value)),
+            _ => return Err(invalid(number, format!("unknown setting '{}' in [{}]", key, window.name))),
+        }
+        // The grid comes before the first section, so it is known here
+        if let (Some((col, row)), Some((cols, rows)), "cell" | "span") = (window.cell, layout.grid, key) {
+            if col + window.span.0 > cols || row + window.span.1 > rows {
+                return Err(invalid(
+                    number,
+                    format!(
+                        "[{}] does not fit the {}x{} grid: cell {},{} spanning {}x{}",
+                        window.name, cols, rows, col, row, window.span.0, window.span.1
+                    ),
+                ));
+            }
+        }
+    }
+
+    for window in &layout.windows {
+        if window.command.is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: [{}] has no command", path.display(), window.name),
+            ));
+        }
+        if window.cell.is_some() && layout.grid.is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: [{}] uses a cell but the layout has no grid", path.display(), window.name),
+            ));
+        }
+    }
+    Ok(layout)
+}
+
+// A layout named on the command line: the file as given, or else one in the
+// layouts folder next to config.toml; ".toml" may be left out ("dev" finds
+// dev.toml here or layouts/dev.toml)
+pub fn resolve(name: &str) -> PathBuf {
+    let given = PathBuf::from(name);
+    let folder = get_config_path().with_file_name("layouts");
+    let file = format!("{}.toml", name);
+    [given.clone(), PathBuf::from(&file), folder.join(name), folder.join(&file)]
+        .into_iter()
+        .find(|path| path.is_file())
+        .unwrap_or(given)
+}
+
+// Sessions are tagged with the layout's full path, so `down` finds them from
+// any directory
+fn key(path: &Path) -> String {
+    dunce::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()).display().to_string()
+}
+
+// Start the layout's popups in order. Ones still running from an earlier
+// `up` are left alone, so this also brings back popups that were closed.
+pub fn up(path: &Path, config: &Config) -> io::Result<()> {
+    let content =
+        fs::read_to_string(path).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+    let layout = parse(path, &content)?;
+    let key = key(path);
+    let base = PathBuf::from(&key).parent().map(Path::to_path_buf).unwrap_or_default();
+
+    let running = runtime::sessions();
+    // Without a known screen, assume a common full-HD one
+    let area = layout.grid.map(|_| {
+        screen::select(layout.monitor.as_ref()).unwrap_or(screen::Rect { x: 0, y: 0, width: 1920, height: 1080 })
+    });
+
+    let mut failed = 0;
+    for window in &layout.windows {
+        let tag = (key.clone(), window.name.clone());
+        if running.iter().any(|s| s.layout.as_ref() == Some(&tag)) {
+            println!("{}: already running", window.name);
+            continue;
+        }
+        thread::sleep(window.delay);
+
+        let mut args = vec!["--title".to_string(), window.name.clone()];
+        args.extend(window.options.iter().cloned());
+        if let (Some((col, row)), Some((cols, rows)), Some(area)) = (window.cell, layout.grid, area) {
+            let (width, height) = (area.width / cols, area.height / rows);
+            args.extend([
+                "--size".to_string(),
+                format!("{}x{}", width * window.span.0, height * window.span.1),
+                "--pos".to_string(),
+                format!("{},{}", area.x + (width * col) as i32, area.y + (height * row) as i32),
+            ]);
+        }
+        args.push("--".to_string());
+        args.extend(window.command.clone());
+
+        let result = cli::parse(&args).and_then(|invocation| match invocation {
+            cli::Invocation::Run { mut options, app, args } => {
+                options.layout = Some(tag);
+                // Relative paths in the command are relative to the popup's
+                // directory, which is also where it starts
+                let cwd = base.join(window.cwd.clone().unwrap_or_default());
+                if !cwd.is_dir() {
+                    return Err(format!("{}: not a directory", cwd.display()));
+                }
+                options.client_dir = Some(cwd);
+                let app = app.unwrap_or_default();
+                launch_command(&app, &args, config, &options).map(|_| ()).map_err(|e| e.to_string())
+            }
+            _ => Err("not a command".to_string()),
+        });
+        match result {
+            Ok(()) => println!("{}: started", window.name),
+            Err(e) => {
+                eprintln!("{}: {}", window.name, e);
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("{} of {} popups could not be started", failed, layout.windows.len()),
+        ));
+    }
+    Ok(())
+}
+
+// Close the popups a layout started, or those of every layout without a file
+pub fn down(path: Option<&Path>) -> io::Result<()> {
+    let key = path.map(key);
+    let sessions = runtime::sessions();
+    let started: Vec<&runtime::Session> = sessions
+        .iter()
+        .filter(|s| match (&s.layout, &key) {
+            (Some((file, _)), Some(key)) => file == key,
+            (Some(_), None) => true,
+            (None, _) => false,
+        })
+        .collect();
+    if started.is_empty() {
+        println!("No popups from {} are running", key.as_deref().unwrap_or("a layout"));
+        return Ok(());
+    }
+
+    let mut failed = 0;
+    for session in started {
+        let name = session.layout.as_ref().map(|(_, name)| name.as_str()).unwrap_or_default();
+        match runtime::kill(session) {
+            Ok(()) => println!("{}: closed", name),
+            Err(e) => {
+                eprintln!("{}: {}", name, e);
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(io::Error::new(io::ErrorKind::Other, format!("{} popups could not be closed", failed)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cells_must_fit_the_grid() {
+        let path = Path::new("dev.toml");
+        let layout = |windows: &str| parse(path, &format!("grid = \"3x2\"\n[a]\ncommand = \"htop\"\n{}", windows));
+        assert!(layout("cell = \"1,0\"\nspan = \"2x2\"").is_ok());
+        let error = |windows: &str| layout(windows).err().map(|e| e.to_string()).unwrap_or_default();
+        assert_eq!(error("cell = \"5,0\""), "dev.toml:4: [a] does not fit the 3x2 grid: cell 5,0 spanning 1x1");
+        assert!(error("span = \"2x1\"\ncell = \"2,1\"").starts_with("dev.toml:5: [a] does not fit"));
+        assert!(error("cell = \"0,1\"\nspan = \"1x2\"").starts_with("dev.toml:5: [a] does not fit"));
+    }
+}
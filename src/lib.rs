@@ -0,0 +1,42 @@
+// src/lib.rs - The rund library: configuration, geometry and pause
+// resolution, and launching popups through `Launcher`. The rund binary is
+// `cli_main` on top.
+//
+// A launch (launch.rs) resolves the config (config.rs), wraps the command in
+// a script that pauses and reports back (wrapper.rs) and opens it in a
+// terminal (terminal.rs).
+mod cells;
+mod cli;
+mod commands;
+mod config;
+#[cfg(all(unix, not(target_os = "macos")))]
+mod dbus;
+#[cfg(not(target_os = "windows"))]
+mod daemon;
+mod environment;
+mod history;
+mod hooks;
+mod glob;
+mod json;
+mod launch;
+mod layout;
+mod log;
+mod notify;
+mod paths;
+mod placement;
+mod quickfix;
+mod runtime;
+mod screen;
+mod shell;
+mod terminal;
+mod watch;
+mod wrapper;
+
+pub use config::{AppGeometry, Config, PauseBehavior};
+pub use launch::{Event, Events, LaunchRequest, Launcher, Session};
+
+/// The rund command line, as the rund binary runs it: `args` are the
+/// arguments after the program name. Exits the process when a command fails.
+pub fn cli_main(args: Vec<String>) {
+    commands::main(args)
+}
@@ -0,0 +1,230 @@
+// src/log.rs - Leveled logging of what rund does: to stderr at the level
+// -v/-vv or RUND_LOG ask for, and with log_file = true in config.toml also to
+// rund.log next to the config, which is rotated as it grows
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::runtime;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    pub fn parse(value: &str) -> Option<Level> {
+        match value.trim().to_lowercase().as_str() {
+            "error" => Some(Level::Error),
+            "warn" | "warning" => Some(Level::Warn),
+            "info" => Some(Level::Info),
+            "debug" => Some(Level::Debug),
+            "trace" => Some(Level::Trace),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Info => "info",
+            Level::Debug => "debug",
+            Level::Trace => "trace",
+        }
+    }
+}
+
+// A log file larger than this is moved to rund.log.1 (and that one to
+// rund.log.2, ...) before the next line is written
+const MAX_SIZE: u64 = 1024 * 1024;
+const KEEP: usize = 3;
+// Records held while it is not known yet whether there is a log file
+const EARLY_LIMIT: usize = 1000;
+
+struct Logger {
+    // None until init(), or the first record when rund is used as a library
+    stderr: Option<Level>,
+    file: Option<(PathBuf, Level)>,
+    // Records from before the config was read, for the log file it may ask
+    // for; only held after hold_for_config()
+    early: Option<Vec<(Level, String)>>,
+}
+
+static LOGGER: Mutex<Logger> = Mutex::new(Logger { stderr: None, file: None, early: None });
+
+fn logger() -> std::sync::MutexGuard<'static, Logger> {
+    LOGGER.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+// RUND_LOG=debug, or warnings and errors only
+fn env_level() -> Level {
+    std::env::var("RUND_LOG").ok().and_then(|value| Level::parse(&value)).unwrap_or(Level::Warn)
+}
+
+// Each -v shows one level more on stderr than RUND_LOG (or the default) does
+pub fn init(verbosity: u8) {
+    let level = match (env_level() as u8).saturating_add(verbosity) {
+        0 => Level::Error,
+        1 => Level::Warn,
+        2 => Level::Info,
+        3 => Level::Debug,
+        _ => Level::Trace,
+    };
+    logger().stderr = Some(level);
+}
+
+// Hold records back from now on until set_file says whether there is a log
+// file, for a command about to read the config. Nothing else holds records,
+// so without a config there is nothing left waiting for a file.
+pub fn hold_for_config() {
+    logger().early.get_or_insert_with(Vec::new);
+}
+
+// The log file from the config (None: there is none), written with records at
+// `level` or more important, and at least those shown on stderr. Records
+// from before are written to it now.
+pub fn set_file(path: Option<PathBuf>, level: Level) {
+    let mut logger = logger();
+    let early = logger.early.take().unwrap_or_default();
+    let stderr = *logger.stderr.get_or_insert_with(env_level);
+    logger.file = path.map(|path| (path, level.max(stderr)));
+    if let Some((ref path, level)) = logger.file {
+        for (record_level, message) in early {
+            if record_level <= level {
+                append(path, record_level, &message);
+            }
+        }
+    }
+}
+
+pub fn write(level: Level, args: fmt::Arguments) {
+    let mut logger = logger();
+    let stderr = *logger.stderr.get_or_insert_with(env_level);
+    let to_file = match (&logger.early, &logger.file) {
+        (Some(_), _) => true,
+        (None, Some((_, file_level))) => level <= *file_level,
+        (None, None) => false,
+    };
+    if level > stderr && !to_file {
+        return;
+    }
+    let message = args.to_string();
+    if level <= stderr {
+        eprintln!("rund: {}: {}", level.name(), message);
+    }
+    emit(&mut logger, level, message);
+}
+
+// An error show_error already put in front of the user, for the log file
+pub fn shown_error(message: &str) {
+    emit(&mut logger(), Level::Error, message.to_string());
+}
+
+fn emit(logger: &mut Logger, level: Level, message: String) {
+    if let Some(ref mut early) = logger.early {
+        if early.len() < EARLY_LIMIT {
+            early.push((level, message));
+        }
+    } else if let Some((ref path, file_level)) = logger.file {
+        if level <= file_level {
+            append(path, level, &message);
+        }
+    }
+}
+
+fn rotate(path: &Path) {
+    let numbered = |n: usize| PathBuf::from(format!("{}.{}", path.display(), n));
+    for n in (1..KEEP).rev() {
+        fs::rename(numbered(n), numbered(n + 1)).ok();
+    }
+    fs::rename(path, numbered(1)).ok();
+}
+
+// Logging never fails a launch: a log file that cannot be written is skipped
+fn append(path: &Path, level: Level, message: &str) {
+    if fs::metadata(path).is_ok_and(|m| m.len() > MAX_SIZE) {
+        rotate(path);
+    }
+    if let Ok(mut file) = fs::OpenOptions::new().create(true).append(true).open(path) {
+        writeln!(file, "{} {:>7} {:<5} {}", timestamp(), std::process::id(), level.name(), message).ok();
+    }
+}
+
+// UTC "2024-05-01 13:45:07Z", from the days since 1970-01-01 (Howard
+// Hinnant's civil_from_days)
+fn timestamp() -> String {
+    let secs = runtime::now_secs();
+    let (days, rest) = ((secs / 86400) as i64, secs % 86400);
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rest / 3600,
+        rest % 3600 / 60,
+        rest % 60
+    )
+}
+
+// `log::info!("...", ...)` and so on, formatted only when the record is kept.
+// Errors reach the log through shown_error.
+macro_rules! warning {
+    ($($arg:tt)*) => { $crate::log::write($crate::log::Level::Warn, format_args!($($arg)*)) };
+}
+macro_rules! info {
+    ($($arg:tt)*) => { $crate::log::write($crate::log::Level::Info, format_args!($($arg)*)) };
+}
+macro_rules! debug {
+    ($($arg:tt)*) => { $crate::log::write($crate::log::Level::Debug, format_args!($($arg)*)) };
+}
+// Only the search for a Linux terminal traces so far
+#[cfg_attr(not(target_os = "linux"), allow(unused_macros))]
+macro_rules! trace {
+    ($($arg:tt)*) => { $crate::log::write($crate::log::Level::Trace, format_args!($($arg)*)) };
+}
+#[cfg_attr(not(target_os = "linux"), allow(unused_imports))]
+pub(crate) use {debug, info, trace};
+// `warn` alone is taken by the lint attribute
+pub(crate) use warning as warn;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_are_held_only_for_the_config() {
+        // Nobody asked to hold them: nothing waits for a log file
+        info!("before any config");
+        assert!(logger().early.is_none());
+
+        let path = std::env::temp_dir().join(format!("rund-test-log-{}.log", std::process::id()));
+        hold_for_config();
+        warn!("held for the file");
+        debug!("too detailed for it");
+        set_file(Some(path.clone()), Level::Info);
+        info!("written right away");
+        set_file(None, Level::Info);
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.contains(" warn  held for the file"));
+        assert!(content.contains(" info  written right away"));
+        assert!(!content.contains("before any config") && !content.contains("too detailed"));
+        fs::remove_file(&path).unwrap();
+    }
+}
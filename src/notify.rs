@@ -0,0 +1,230 @@
+// src/notify.rs - Desktop notifications when a popup's command finishes
+use std::fmt;
+use std::io;
+use std::process::Command;
+
+// When to notify: `notify = "always" | "on_error" | "if_longer_than:30s"`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Notify {
+    #[default]
+    Never,
+    Always,
+    OnError,
+    // Seconds
+    LongerThan(u64),
+}
+
+// "30s", "2m", "1h" or plain seconds
+fn parse_secs(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(at) => value.split_at(at),
+        None => (value, "s"),
+    };
+    let factor = match unit.trim() {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        _ => return None,
+    };
+    Some(number.parse::<u64>().ok()? * factor)
+}
+
+impl Notify {
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim().to_lowercase().replace('-', "_");
+        match value.as_str() {
+            "never" | "false" => Some(Notify::Never),
+            "always" | "true" => Some(Notify::Always),
+            "on_error" => Some(Notify::OnError),
+            _ => value.strip_prefix("if_longer_than:").and_then(parse_secs).map(Notify::LongerThan),
+        }
+    }
+
+    // An exit code of None (not known) does not count as an error
+    pub fn wanted(self, exit_code: Option<i64>, secs: u64) -> bool {
+        match self {
+            Notify::Never => false,
+            Notify::Always => true,
+            Notify::OnError => exit_code.is_some_and(|code| code != 0),
+            Notify::LongerThan(limit) => secs > limit,
+        }
+    }
+}
+
+// Written back in the form parse() takes
+impl fmt::Display for Notify {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Notify::Never => f.write_str("never"),
+            Notify::Always => f.write_str("always"),
+            Notify::OnError => f.write_str("on_error"),
+            Notify::LongerThan(secs) => write!(f, "if_longer_than:{}s", secs),
+        }
+    }
+}
+
+// "45s", "2m 05s", "1h 02m"
+pub fn duration(secs: u64) -> String {
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m {:02}s", secs / 60, secs % 60),
+        _ => format!("{}h {:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+// The notification for a finished command: the command as summary, how it
+// ended in the body
+pub fn completed(command: &str, exit_code: Option<i64>, secs: u64) -> io::Result<()> {
+    let failed = exit_code.is_some_and(|code| code != 0);
+    let body = match exit_code {
+        Some(code) => format!("Exit code {} after {}", code, duration(secs)),
+        None => format!("Finished after {}", duration(secs)),
+    };
+    send(command, &body, failed)
+}
+
+// The freedesktop Notifications service over D-Bus, or notify-send when that
+// does not answer
+#[cfg(all(unix, not(target_os = "macos")))]
+fn send(summary: &str, body: &str, failed: bool) -> io::Result<()> {
+    use crate::dbus;
+
+    // Notify(app_name, replaces_id, app_icon, summary, body, actions, hints,
+    // expire_timeout)
+    let mut args = dbus::Writer::default();
+    args.string("rund");
+    args.u32(0);
+    args.string(if failed { "dialog-error" } else { "dialog-information" });
+    args.string(summary);
+    args.string(body);
+    args.array(4, |_| {});
+    args.array(8, |hints| {
+        hints.structure(|entry| {
+            entry.string("urgency");
+            entry.signature("y");
+            // Failures stay until dismissed
+            entry.byte(if failed { 2 } else { 1 });
+        })
+    });
+    args.i32(-1);
+
+    let call = dbus::Call {
+        destination: "org.freedesktop.Notifications",
+        path: "/org/freedesktop/Notifications",
+        interface: "org.freedesktop.Notifications",
+        member: "Notify",
+        signature: "susssasa{sv}i",
+        body: args,
+    };
+    dbus::call(&call).or_else(|e| {
+        let status = Command::new("notify-send")
+            .args(["--app-name", "rund", "--urgency", if failed { "critical" } else { "normal" }])
+            .arg("--")
+            .args([summary, body])
+            .status()
+            .map_err(|_| e)?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(io::Error::new(io::ErrorKind::Other, "notify-send failed"))
+        }
+    })
+}
+
+#[cfg(target_os = "macos")]
+fn send(summary: &str, body: &str, _failed: bool) -> io::Result<()> {
+    let quote = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+    let script = format!(
+        r#"display notification "{}" with title "rund" subtitle "{}""#,
+        quote(body),
+        quote(summary)
+    );
+    let status = Command::new("osascript").arg("-e").arg(&script).status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::new(io::ErrorKind::Other, "osascript failed"))
+    }
+}
+
+// A balloon from the notification area. PowerShell keeps the icon up while it
+// shows, so it runs on without a console of its own, past the popup closing.
+#[cfg(target_os = "windows")]
+fn send(summary: &str, body: &str, failed: bool) -> io::Result<()> {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+    let quote = |s: &str| format!("'{}'", s.replace('\'', "''"));
+    let script = format!(
+        "Add-Type -AssemblyName System.Windows.Forms; \
+         $n = New-Object System.Windows.Forms.NotifyIcon; \
+         $n.Icon = [System.Drawing.SystemIcons]::{}; $n.Visible = $true; \
+         $n.ShowBalloonTip(10000, {}, {}, '{}'); Start-Sleep 10; $n.Dispose()",
+        if failed { "Error" } else { "Information" },
+        quote(summary),
+        quote(body),
+        if failed { "Error" } else { "Info" }
+    );
+    Command::new("powershell")
+        .args(["-NoProfile", "-Command", &script])
+        .creation_flags(CREATE_NO_WINDOW)
+        .spawn()?;
+    Ok(())
+}
+
+#[cfg(all(test, unix, not(target_os = "macos")))]
+mod tests {
+    use super::*;
+    use crate::dbus::tests::{fake_bus, Reader, BUS};
+    use std::env;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn notifies_over_the_session_bus() {
+        let _bus = BUS.lock().unwrap_or_else(|e| e.into_inner());
+        let bus = fake_bus("notify", None);
+        completed("cargo test", Some(101), 65).unwrap();
+        let session = bus.join().unwrap();
+
+        assert!(session.auth.starts_with("\0AUTH EXTERNAL "));
+        let notify = &session.calls[1];
+        assert_eq!((session.calls[0].member.as_str(), notify.member.as_str()), ("Hello", "Notify"));
+        assert_eq!(notify.signature, "susssasa{sv}i");
+        let mut body = Reader { buf: &notify.body, pos: 0 };
+        let (app, replaces, icon) = (body.string(), body.u32(), body.string());
+        assert_eq!((app.as_str(), replaces, icon.as_str()), ("rund", 0, "dialog-error"));
+        assert_eq!((body.string(), body.string()), ("cargo test".to_string(), "Exit code 101 after 1m 05s".to_string()));
+        assert_eq!(body.u32(), 0, "no actions");
+        // Failures are critical: the one hint is urgency = byte 2
+        body.u32();
+        body.pos = (body.pos + 7) / 8 * 8;
+        assert_eq!((body.string(), body.signature(), body.buf[body.pos]), ("urgency".to_string(), "y".to_string(), 2));
+    }
+
+    #[test]
+    fn falls_back_to_notify_send() {
+        let _bus = BUS.lock().unwrap_or_else(|e| e.into_inner());
+        let bin = env::temp_dir().join(format!("rund-test-notify-send-{}", std::process::id()));
+        fs::create_dir_all(&bin).unwrap();
+        let script = bin.join("notify-send");
+        fs::write(&script, "#!/bin/sh\nprintf '%s\\n' \"$@\" > \"$(dirname \"$0\")/args\"\n").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        let path = env::var_os("PATH").unwrap_or_default();
+        let mut dirs = vec![bin.clone()];
+        dirs.extend(env::split_paths(&path));
+        env::set_var("PATH", env::join_paths(dirs).unwrap());
+
+        let bus = fake_bus("fallback", Some("Notify"));
+        let sent = completed("make", Some(0), 3);
+        env::set_var("PATH", path);
+        sent.unwrap();
+        let session = bus.join().unwrap();
+
+        assert_eq!(session.calls.last().map(|c| c.member.as_str()), Some("Notify"));
+        let args = fs::read_to_string(bin.join("args")).unwrap();
+        assert_eq!(args, "--app-name\nrund\n--urgency\nnormal\n--\nmake\nExit code 0 after 3s\n");
+        fs::remove_dir_all(&bin).ok();
+    }
+}
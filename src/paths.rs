@@ -0,0 +1,119 @@
+// src/paths.rs - Path arguments: absolute paths and file:line:col targets
+use std::path::{Path, PathBuf};
+
+use crate::shell::Shell;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Target {
+    pub path: PathBuf,
+    pub line: Option<u32>,
+    pub col: Option<u32>,
+}
+
+// How editors take a position when none is configured with goto_line.
+// {file}, {line} and {col} are filled in; a missing column becomes 1.
+const GOTO_TEMPLATES: [(&str, &str); 16] = [
+    ("vi", "+{line} {file}"),
+    ("vim", "+{line} {file}"),
+    ("nvim", "+{line} {file}"),
+    ("gvim", "+{line} {file}"),
+    ("hx", "{file}:{line}:{col}"),
+    ("helix", "{file}:{line}:{col}"),
+    ("code", "-g {file}:{line}:{col}"),
+    ("codium", "-g {file}:{line}:{col}"),
+    ("subl", "{file}:{line}:{col}"),
+    ("micro", "+{line}:{col} {file}"),
+    ("nano", "+{line},{col} {file}"),
+    ("emacs", "+{line}:{col} {file}"),
+    ("emacsclient", "+{line}:{col} {file}"),
+    ("kak", "+{line}:{col} {file}"),
+    ("less", "+{line}g {file}"),
+    ("bat", "--highlight-line {line} {file}"),
+];
+
+pub fn builtin_template(exe: &str) -> Option<&'static str> {
+    GOTO_TEMPLATES.iter().find(|(name, _)| *name == exe).map(|(_, template)| *template)
+}
+
+// Absolute, with symlinks resolved when the file exists; for a new file its
+// directory is resolved instead
+fn resolve(path: &Path, cwd: &Path) -> PathBuf {
+    let joined = cwd.join(path);
+    if let Ok(abs) = dunce::canonicalize(&joined) {
+        return abs;
+    }
+    match (joined.parent(), joined.file_name()) {
+        (Some(dir), Some(name)) => dunce::canonicalize(dir).map(|dir| dir.join(name)).unwrap_or(joined),
+        _ => joined,
+    }
+}
+
+// Split "src/main.rs:120:5" or "src/main.rs:120" from compiler and grep
+// output. Drive letters survive since only trailing numbers are taken.
+fn split_position(arg: &str) -> Option<(&str, u32, Option<u32>)> {
+    let (rest, last) = arg.rsplit_once(':')?;
+    let last: u32 = last.parse().ok()?;
+    match rest.rsplit_once(':') {
+        Some((path, line)) if !path.is_empty() => match line.parse() {
+            Ok(line) => Some((path, line, Some(last))),
+            Err(_) => Some((rest, last, None)),
+        },
+        _ if !rest.is_empty() => Some((rest, last, None)),
+        _ => None,
+    }
+}
+
+// Something that reads as a file name rather than a word: it has a
+// directory part or an extension
+fn looks_like_path(arg: &str) -> bool {
+    let path = Path::new(arg);
+    arg.contains(['/', '\\'])
+        || path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext.chars().any(|c| c.is_ascii_alphabetic()))
+}
+
+// Recognize an argument that names a file: an existing path, an existing
+// file with a :line[:col] suffix, or (for editors, which may create it) a
+// new file in an existing directory
+pub fn parse_target(arg: &str, cwd: &Path, may_create: bool) -> Option<Target> {
+    if arg.starts_with('-') || arg.contains("://") {
+        return None;
+    }
+    if cwd.join(arg).exists() {
+        return Some(Target { path: resolve(Path::new(arg), cwd), line: None, col: None });
+    }
+    if let Some((path, line, col)) = split_position(arg) {
+        if cwd.join(path).is_file() {
+            return Some(Target { path: resolve(Path::new(path), cwd), line: Some(line), col });
+        }
+    }
+    if may_create && looks_like_path(arg) {
+        let path = resolve(Path::new(arg), cwd);
+        if path.parent().is_some_and(|dir| dir.is_dir()) {
+            return Some(Target { path, line: None, col: None });
+        }
+    }
+    None
+}
+
+impl Target {
+    // The target as the app's arguments in `shell`'s syntax: the goto
+    // template when there is a position to go to, otherwise the path
+    // (keeping the position as text)
+    pub fn render(&self, template: Option<&str>, shell: &Shell) -> String {
+        let file = self.path.display().to_string();
+        match (self.line, template) {
+            (Some(line), Some(template)) => template
+                .replace("{file}", &shell.arg(&file))
+                .replace("{line}", &line.to_string())
+                .replace("{col}", &self.col.unwrap_or(1).to_string()),
+            (Some(line), None) => match self.col {
+                Some(col) => shell.arg(&format!("{}:{}:{}", file, line, col)),
+                None => shell.arg(&format!("{}:{}", file, line)),
+            },
+            (None, _) => shell.arg(&file),
+        }
+    }
+}
@@ -0,0 +1,114 @@
+// src/placement.rs - Where to put a popup when other rund windows are open
+use crate::screen::Rect;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Placement {
+    // Always the configured position
+    None,
+    // Each new window offset diagonally from the previous one
+    Cascade,
+    // First free cell of a grid over the monitor
+    Tile,
+    // Configured position unless taken, else the first free spot
+    AvoidOverlap,
+}
+
+impl Placement {
+    pub fn parse(value: &str) -> Self {
+        match value.to_lowercase().replace('_', "-").as_str() {
+            "cascade" => Placement::Cascade,
+            "tile" | "grid" => Placement::Tile,
+            "avoid-overlap" | "avoid" => Placement::AvoidOverlap,
+            _ => Placement::None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PlacementOptions {
+    pub cascade_offset: i32,
+    pub tile_columns: u32,
+    pub tile_rows: u32,
+}
+
+fn overlaps(a: &Rect, b: &Rect) -> bool {
+    a.x < b.x + b.width as i32
+        && b.x < a.x + a.width as i32
+        && a.y < b.y + b.height as i32
+        && b.y < a.y + a.height as i32
+}
+
+// Pick the rectangle for a new window. `wanted` is the configured geometry,
+// `open` the rectangles of rund windows that are still open.
+pub fn place(strategy: Placement, wanted: Rect, screen: Rect, open: &[Rect], options: &PlacementOptions) -> Rect {
+    match strategy {
+        Placement::None => wanted,
+        Placement::Cascade => cascade(wanted, screen, open, options.cascade_offset),
+        Placement::Tile => tile(screen, open, options.tile_columns, options.tile_rows),
+        Placement::AvoidOverlap => avoid_overlap(wanted, screen, open, options.cascade_offset),
+    }
+}
+
+// Step down-right from the configured spot until no open window starts at
+// the same place, wrapping around before the window would leave the screen
+fn cascade(wanted: Rect, screen: Rect, open: &[Rect], offset: i32) -> Rect {
+    let offset = offset.max(1);
+    let room_x = (screen.x + screen.width as i32 - wanted.width as i32 - wanted.x) / offset;
+    let room_y = (screen.y + screen.height as i32 - wanted.height as i32 - wanted.y) / offset;
+    let steps = room_x.min(room_y).max(0) + 1;
+
+    let at = |step: i32| Rect {
+        x: wanted.x + step * offset,
+        y: wanted.y + step * offset,
+        ..wanted
+    };
+    let taken = |candidate: &Rect| {
+        open.iter()
+            .any(|r| (r.x - candidate.x).abs() < offset / 2 + 1 && (r.y - candidate.y).abs() < offset / 2 + 1)
+    };
+
+    (0..steps)
+        .map(at)
+        .find(|candidate| !taken(candidate))
+        .unwrap_or_else(|| at(open.len() as i32 % steps))
+}
+
+// Split the monitor into a grid and take the first cell no open window overlaps
+fn tile(screen: Rect, open: &[Rect], columns: u32, rows: u32) -> Rect {
+    let columns = columns.max(1);
+    let rows = rows.max(1);
+    let width = screen.width / columns;
+    let height = screen.height / rows;
+
+    let cell = |index: u32| Rect {
+        x: screen.x + ((index % columns) * width) as i32,
+        y: screen.y + ((index / columns) * height) as i32,
+        width,
+        height,
+    };
+
+    let count = columns * rows;
+    (0..count)
+        .map(cell)
+        .find(|candidate| !open.iter().any(|r| overlaps(r, candidate)))
+        .unwrap_or_else(|| cell(open.len() as u32 % count))
+}
+
+// Keep the configured spot if it is free, otherwise scan the monitor row by
+// row for a free one, and cascade when the screen is full
+fn avoid_overlap(wanted: Rect, screen: Rect, open: &[Rect], step: i32) -> Rect {
+    let free = |candidate: &Rect| !open.iter().any(|r| overlaps(r, candidate));
+    if free(&wanted) {
+        return wanted;
+    }
+
+    let step = step.max(8) as usize;
+    let max_x = screen.x + screen.width as i32 - wanted.width as i32;
+    let max_y = screen.y + screen.height as i32 - wanted.height as i32;
+    (screen.y..=max_y.max(screen.y))
+        .step_by(step)
+        .flat_map(|y| (screen.x..=max_x.max(screen.x)).step_by(step).map(move |x| (x, y)))
+        .map(|(x, y)| Rect { x, y, ..wanted })
+        .find(|candidate| free(candidate))
+        .unwrap_or_else(|| cascade(wanted, screen, open, step as i32))
+}
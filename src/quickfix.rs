@@ -0,0 +1,280 @@
+// src/quickfix.rs - File locations from compiler, grep and test output
+use std::io::{self, Write};
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub path: String,
+    pub line: u32,
+    pub col: Option<u32>,
+    pub message: String,
+}
+
+impl Entry {
+    // "path:line[:col]", the form the path rewriting understands
+    pub fn location(&self) -> String {
+        match self.col {
+            Some(col) => format!("{}:{}:{}", self.path, self.line, col),
+            None => format!("{}:{}", self.path, self.line),
+        }
+    }
+}
+
+fn number(value: &str) -> Option<u32> {
+    value.trim().parse().ok().filter(|&n| n > 0)
+}
+
+// "path:line:col: message" (gcc, clang, rg --column), "path:line: message"
+// and "path:line:text" (grep -n, rg -n, pytest)
+fn parse_colon(line: &str) -> Option<Entry> {
+    // Skip a drive letter so "C:\src\x.c:3:1: ..." splits in the right place
+    let start = match line.as_bytes() {
+        [drive, b':', b'\\' | b'/', ..] if drive.is_ascii_alphabetic() => 2,
+        _ => 0,
+    };
+    let mut parts = line[start..].splitn(3, ':');
+    let path = format!("{}{}", &line[..start], parts.next()?);
+    let line_no = number(parts.next()?)?;
+    let rest = parts.next().unwrap_or("");
+    let (col, message) = match rest.split_once(':') {
+        Some((col, message)) if number(col).is_some() => (number(col), message),
+        _ => (None, rest),
+    };
+    Some(Entry { path, line: line_no, col, message: message.trim().to_string() })
+}
+
+// rustc: "  --> src/main.rs:120:5", the message is on an earlier line
+fn parse_rustc(line: &str, message: &str) -> Option<Entry> {
+    let location = line.trim_start().strip_prefix("--> ")?;
+    let mut entry = parse_colon(&format!("{}: ", location.trim()))?;
+    entry.message = message.to_string();
+    Some(entry)
+}
+
+// Python tracebacks: '  File "app/x.py", line 12, in main'
+fn parse_python(line: &str) -> Option<Entry> {
+    let rest = line.trim_start().strip_prefix("File \"")?;
+    let (path, rest) = rest.split_once("\", line ")?;
+    let (line_no, message) = rest.split_once(", ").unwrap_or((rest, ""));
+    Some(Entry { path: path.to_string(), line: number(line_no)?, col: None, message: message.trim().to_string() })
+}
+
+// MSVC and C#: "src\x.cpp(12,5): error C2065: ..."
+fn parse_msvc(line: &str) -> Option<Entry> {
+    let (path, rest) = line.split_once('(')?;
+    let (position, message) = rest.split_once("):")?;
+    let (line_no, col) = match position.split_once(',') {
+        Some((line_no, col)) => (number(line_no)?, number(col)),
+        None => (number(position)?, None),
+    };
+    Some(Entry { path: path.trim().to_string(), line: line_no, col, message: message.trim().to_string() })
+}
+
+// Entries whose file exists relative to `cwd`, in input order without repeats
+pub fn parse(text: &str, cwd: &Path) -> Vec<Entry> {
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut last_message = String::new();
+
+    for line in text.lines() {
+        let entry = parse_rustc(line, &last_message)
+            .or_else(|| parse_python(line))
+            .or_else(|| parse_msvc(line))
+            .or_else(|| parse_colon(line));
+
+        match entry {
+            Some(entry) if cwd.join(&entry.path).is_file() => {
+                let seen = entries.iter().any(|e| e.path == entry.path && e.line == entry.line && e.col == entry.col);
+                if !seen {
+                    entries.push(entry);
+                }
+            }
+            _ if !line.trim().is_empty() && !line.starts_with(' ') => last_message = line.trim().to_string(),
+            _ => {}
+        }
+    }
+    entries
+}
+
+fn describe(entry: &Entry, width: usize) -> String {
+    let text = format!("{}  {}", entry.location(), entry.message);
+    text.chars().take(width).collect()
+}
+
+// Pick an entry with the arrow keys (or j/k) and Enter; q cancels. Keys come
+// from the controlling terminal since stdin is the quickfix stream.
+#[cfg(not(target_os = "windows"))]
+pub fn pick(entries: &[Entry]) -> io::Result<Option<usize>> {
+    use std::fs::OpenOptions;
+    use std::io::Read;
+    use std::process::Command;
+
+    let mut tty = OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+    let stty = |args: &[&str]| -> io::Result<String> {
+        let output = Command::new("stty").args(args).stdin(OpenOptions::new().read(true).open("/dev/tty")?).output()?;
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+    let saved = stty(&["-g"])?;
+    let width = stty(&["size"])?
+        .split_whitespace()
+        .nth(1)
+        .and_then(|cols| cols.parse::<usize>().ok())
+        .filter(|&cols| cols > 0)
+        .unwrap_or(80)
+        .saturating_sub(3);
+    // -isig so Ctrl-C arrives as a key and the terminal is always restored
+    stty(&["-icanon", "-echo", "-isig", "min", "1"])?;
+
+    let visible = entries.len().min(15);
+    let mut selected = 0;
+    let mut top = 0;
+    let mut key = [0u8; 1];
+
+    let mut choose = || -> io::Result<Option<usize>> {
+        write!(tty, "\x1b[?25l")?;
+        loop {
+            if selected < top {
+                top = selected;
+            } else if selected >= top + visible {
+                top = selected + 1 - visible;
+            }
+            for (i, entry) in entries.iter().enumerate().skip(top).take(visible) {
+                if i == selected {
+                    write!(tty, "\x1b[2K\x1b[7m> {}\x1b[0m\r\n", describe(entry, width))?;
+                } else {
+                    write!(tty, "\x1b[2K  {}\r\n", describe(entry, width))?;
+                }
+            }
+            write!(tty, "\x1b[{}A", visible)?;
+            tty.flush()?;
+
+            if tty.read(&mut key)? == 0 {
+                return Ok(None);
+            }
+            match key[0] {
+                b'\r' | b'\n' => return Ok(Some(selected)),
+                b'q' | 3 => return Ok(None),
+                b'k' => selected = selected.saturating_sub(1),
+                b'j' => selected = (selected + 1).min(entries.len() - 1),
+                // Arrow keys arrive as ESC [ A / ESC [ B
+                0x1b => {
+                    let mut seq = [0u8; 2];
+                    tty.read_exact(&mut seq)?;
+                    match seq {
+                        [b'[', b'A'] => selected = selected.saturating_sub(1),
+                        [b'[', b'B'] => selected = (selected + 1).min(entries.len() - 1),
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+    };
+    let choice = choose();
+
+    write!(tty, "\x1b[J\x1b[?25h")?;
+    stty(&[&saved])?;
+    choice
+}
+
+// The console has no simple raw mode from here, so list the entries and ask
+// for a number instead
+#[cfg(target_os = "windows")]
+pub fn pick(entries: &[Entry]) -> io::Result<Option<usize>> {
+    use std::fs::{File, OpenOptions};
+    use std::io::BufRead;
+
+    let mut console = OpenOptions::new().write(true).open("CONOUT$")?;
+    for (i, entry) in entries.iter().enumerate() {
+        writeln!(console, "{:3}  {}", i + 1, describe(entry, 100))?;
+    }
+    write!(console, "Open which entry (Enter to cancel)? ")?;
+    console.flush()?;
+
+    let mut answer = String::new();
+    io::BufReader::new(File::open("CONIN$")?).read_line(&mut answer)?;
+    Ok(answer
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .filter(|&n| n >= 1 && n <= entries.len())
+        .map(|n| n - 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn entry(path: &str, line: u32, col: Option<u32>, message: &str) -> Entry {
+        Entry { path: path.to_string(), line, col, message: message.to_string() }
+    }
+
+    #[test]
+    fn parses_location_lines() {
+        let cases = [
+            // gcc, clang, rg --vimgrep
+            ("src/x.c:3:14: error: expected ';'", Some(entry("src/x.c", 3, Some(14), "error: expected ';'"))),
+            ("src/main.rs:120:5:    let x = 1;", Some(entry("src/main.rs", 120, Some(5), "let x = 1;"))),
+            // grep -n, rg -n: a colon in the text is not a column
+            ("src/lib.rs:7:    a::b(1:2)", Some(entry("src/lib.rs", 7, None, "a::b(1:2)"))),
+            // pytest's short summary
+            ("tests/test_app.py:42: AssertionError", Some(entry("tests/test_app.py", 42, None, "AssertionError"))),
+            ("C:\\src\\x.c:3:1: warning", Some(entry("C:\\src\\x.c", 3, Some(1), "warning"))),
+            ("  File \"app/x.py\", line 12, in main", Some(entry("app/x.py", 12, None, "in main"))),
+            ("src\\x.cpp(12,5): error C2065: 'y'", Some(entry("src\\x.cpp", 12, Some(5), "error C2065: 'y'"))),
+            ("Program.cs(8): warning CS0168", Some(entry("Program.cs", 8, None, "warning CS0168"))),
+            ("warning: unused variable", None),
+            ("src/x.c:0: line numbers start at 1", None),
+            ("   Compiling rund v0.1.2", None),
+        ];
+        for (line, expected) in cases {
+            let parsed = parse_python(line).or_else(|| parse_msvc(line)).or_else(|| parse_colon(line));
+            assert_eq!(parsed, expected, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn rustc_location_takes_the_message_above() {
+        assert_eq!(
+            parse_rustc("   --> src/main.rs:4:9", "error[E0425]: cannot find value `y`"),
+            Some(entry("src/main.rs", 4, Some(9), "error[E0425]: cannot find value `y`"))
+        );
+        assert_eq!(parse_rustc("src/main.rs:4:9", ""), None);
+    }
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rund-quickfix-{}-{}", std::process::id(), name));
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("src/main.rs"), "").unwrap();
+        fs::write(dir.join("test_app.py"), "").unwrap();
+        dir
+    }
+
+    #[test]
+    fn keeps_existing_files_once_in_order() {
+        let dir = scratch("parse");
+        let output = "\
+error[E0425]: cannot find value `y` in this scope
+ --> src/main.rs:4:9
+  |
+warning: unused import
+ --> src/main.rs:1:5
+src/main.rs:4:9: the same place again
+src/missing.rs:3:1: not a file here
+test_app.py:12: AssertionError
+";
+        let entries = parse(output, &dir);
+        fs::remove_dir_all(&dir).ok();
+        assert_eq!(
+            entries,
+            [
+                entry("src/main.rs", 4, Some(9), "error[E0425]: cannot find value `y` in this scope"),
+                entry("src/main.rs", 1, Some(5), "warning: unused import"),
+                entry("test_app.py", 12, None, "AssertionError"),
+            ]
+        );
+        assert_eq!(entries[0].location(), "src/main.rs:4:9");
+        assert_eq!(entries[2].location(), "test_app.py:12");
+    }
+}
@@ -0,0 +1,673 @@
+// src/runtime.rs - Runtime state shared between rund invocations
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::screen::Rect;
+
+// While a wrapper has not reported its PID yet, trust a fresh entry this long
+const STARTUP_GRACE_SECS: u64 = 30;
+
+// Exit files are left for whoever waits on the session this long
+const EXIT_FILE_SECS: u64 = 24 * 60 * 60;
+
+// Per-user directory for state that only lives as long as the session:
+// $XDG_RUNTIME_DIR/rund, or a rund-<user> folder in the temp dir. Anyone
+// could create the latter first, so on Unix it is created private and
+// checked on every use: completions, the registry and the daemon socket are
+// only used in a directory that is ours and has mode 0700.
+pub fn runtime_dir() -> io::Result<PathBuf> {
+    let dir = match env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir).join("rund"),
+        None => {
+            let user = env::var("USER")
+                .or_else(|_| env::var("USERNAME"))
+                .unwrap_or_else(|_| "user".to_string());
+            env::temp_dir().join(format!("rund-{}", user))
+        }
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+        extern "C" {
+            fn geteuid() -> u32;
+        }
+
+        fs::DirBuilder::new().recursive(true).mode(0o700).create(&dir)?;
+        let meta = fs::symlink_metadata(&dir)?;
+        if !meta.is_dir() || meta.uid() != unsafe { geteuid() } || meta.mode() & 0o777 != 0o700 {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!(
+                    "{} is not a private directory of this user (it must be owned by you with mode 0700); refusing to use it",
+                    dir.display()
+                ),
+            ));
+        }
+    }
+    #[cfg(not(unix))]
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// Unique enough for one user's launches: seconds plus nanoseconds and our PID
+pub fn new_id() -> String {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    format!("{}{:09}-{}", now.as_secs(), now.subsec_nanos(), std::process::id())
+}
+
+#[cfg(target_os = "linux")]
+pub fn process_alive(pid: u32) -> bool {
+    PathBuf::from(format!("/proc/{}", pid)).exists()
+}
+
+#[cfg(target_os = "macos")]
+pub fn process_alive(pid: u32) -> bool {
+    std::process::Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stderr(std::process::Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+#[cfg(target_os = "windows")]
+pub fn process_alive(pid: u32) -> bool {
+    use std::ffi::c_void;
+
+    const PROCESS_QUERY_LIMITED_INFORMATION: u32 = 0x1000;
+    const STILL_ACTIVE: u32 = 259;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn OpenProcess(access: u32, inherit: i32, pid: u32) -> *mut c_void;
+        fn GetExitCodeProcess(process: *mut c_void, code: *mut u32) -> i32;
+        fn CloseHandle(handle: *mut c_void) -> i32;
+    }
+
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if handle.is_null() {
+            return false;
+        }
+        let mut code = 0u32;
+        let ok = GetExitCodeProcess(handle, &mut code) != 0;
+        CloseHandle(handle);
+        ok && code == STILL_ACTIVE
+    }
+}
+
+// Launched popups (sessions) are tracked as an append-only log, so the
+// generated wrappers can write to it without locking:
+//   open    <TAB> id <TAB> x <TAB> y <TAB> width <TAB> height <TAB> created
+//   session <TAB> id <TAB> backend <TAB> positioned 0|1 <TAB> app <TAB> title [<TAB> file]...
+//   server  <TAB> id <TAB> address the app listens on (nvim --listen)
+//   pid     <TAB> id <TAB> pid of the wrapper shell
+//   window  <TAB> id <TAB> X11 window id, or tmux pane id
+//   layout  <TAB> id <TAB> layout file <TAB> window name in it
+//   close   <TAB> id [<TAB> exit code of the command, when the wrapper knows it]
+// rund itself appends and compacts it only while holding lock_registry().
+pub fn windows_file() -> io::Result<PathBuf> {
+    Ok(runtime_dir()?.join("windows.tsv"))
+}
+
+// Where the wrapper writes the exit code of a session's command as it exits,
+// and kill writes "killed". Unlike the close line it survives compaction and
+// does not depend on the history being kept.
+pub fn exit_file(id: &str) -> io::Result<PathBuf> {
+    Ok(runtime_dir()?.join(format!("exit-{}", id)))
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Session {
+    pub id: String,
+    pub rect: Rect,
+    // False when the window manager chose the position, so `rect` only
+    // tells the size
+    pub positioned: bool,
+    pub backend: String,
+    // Executable name of the app, e.g. "nvim"
+    pub app: String,
+    pub title: String,
+    // Files the app was opened on, absolute
+    pub files: Vec<PathBuf>,
+    pub pid: Option<u32>,
+    pub window: Option<String>,
+    pub server: Option<String>,
+    // Layout file and window name, for popups `rund layout up` started
+    pub layout: Option<(String, String)>,
+    pub created: u64,
+}
+
+// Tabs and newlines would split a registry line
+fn field(value: &str) -> String {
+    value.replace(['\t', '\n', '\r'], " ")
+}
+
+fn format_session(session: &Session) -> String {
+    let r = session.rect;
+    let mut lines = format!(
+        "open\t{}\t{}\t{}\t{}\t{}\t{}\n",
+        session.id, r.x, r.y, r.width, r.height, session.created
+    );
+    lines.push_str(&format!(
+        "session\t{}\t{}\t{}\t{}\t{}",
+        session.id,
+        field(&session.backend),
+        if session.positioned { 1 } else { 0 },
+        field(&session.app),
+        field(&session.title)
+    ));
+    for file in &session.files {
+        lines.push_str(&format!("\t{}", field(&file.display().to_string())));
+    }
+    lines.push('\n');
+    if let Some(pid) = session.pid {
+        lines.push_str(&format!("pid\t{}\t{}\n", session.id, pid));
+    }
+    if let Some(ref window) = session.window {
+        lines.push_str(&format!("window\t{}\t{}\n", session.id, window));
+    }
+    if let Some(ref server) = session.server {
+        lines.push_str(&format!("server\t{}\t{}\n", session.id, field(server)));
+    }
+    if let Some((ref file, ref name)) = session.layout {
+        lines.push_str(&format!("layout\t{}\t{}\t{}\n", session.id, field(file), field(name)));
+    }
+    lines
+}
+
+// The sessions in a registry that are still running, oldest first: not
+// closed by their wrapper, and either with a live wrapper PID or too new to
+// have reported one
+fn parse_sessions(content: &str) -> Vec<Session> {
+    let mut sessions: HashMap<String, Session> = HashMap::new();
+    let mut order = Vec::new();
+    // Open lines first: lines carried over by compact() can come after ones
+    // a wrapper appended meanwhile, so a session's other lines may come first
+    let (opens, others): (Vec<&str>, Vec<&str>) = content.lines().partition(|line| line.starts_with("open\t"));
+    for line in opens.into_iter().chain(others) {
+        let fields: Vec<&str> = line.split('\t').collect();
+        match fields[..] {
+            ["open", id, x, y, width, height, created] => {
+                let parsed = (x.parse(), y.parse(), width.parse(), height.parse(), created.parse());
+                if let (Ok(x), Ok(y), Ok(width), Ok(height), Ok(created)) = parsed {
+                    order.push(id.to_string());
+                    sessions.insert(
+                        id.to_string(),
+                        Session {
+                            id: id.to_string(),
+                            rect: Rect { x, y, width, height },
+                            // Only placed popups were registered before sessions
+                            positioned: true,
+                            created,
+                            ..Session::default()
+                        },
+                    );
+                }
+            }
+            ["session", id, backend, positioned, app, title, ref files @ ..] => {
+                if let Some(session) = sessions.get_mut(id) {
+                    session.backend = backend.to_string();
+                    session.positioned = positioned == "1";
+                    session.app = app.to_string();
+                    session.title = title.to_string();
+                    session.files = files.iter().map(PathBuf::from).collect();
+                }
+            }
+            ["pid", id, pid] => {
+                if let Some(session) = sessions.get_mut(id) {
+                    session.pid = pid.trim().parse().ok();
+                }
+            }
+            ["window", id, window] if !window.trim().is_empty() => {
+                if let Some(session) = sessions.get_mut(id) {
+                    session.window = Some(window.trim().to_string());
+                }
+            }
+            ["server", id, server] => {
+                if let Some(session) = sessions.get_mut(id) {
+                    session.server = Some(server.to_string());
+                }
+            }
+            ["layout", id, file, name] => {
+                if let Some(session) = sessions.get_mut(id) {
+                    session.layout = Some((file.to_string(), name.to_string()));
+                }
+            }
+            ["close", id, ..] => {
+                sessions.remove(id.trim());
+            }
+            _ => {}
+        }
+    }
+
+    let now = now_secs();
+    order
+        .iter()
+        .filter_map(|id| sessions.remove(id))
+        .filter(|s| match s.pid {
+            Some(pid) => process_alive(pid),
+            None => now.saturating_sub(s.created) < STARTUP_GRACE_SECS,
+        })
+        .collect()
+}
+
+pub fn sessions() -> Vec<Session> {
+    let content = windows_file().and_then(fs::read_to_string).unwrap_or_default();
+    let open = parse_sessions(&content);
+    // Drop everything that is gone so the log stays small
+    if open.len() * 6 < content.lines().count() {
+        compact().ok();
+    }
+    open
+}
+
+// Held while rund appends to or rewrites the registry; released when dropped
+pub struct RegistryLock {
+    _file: fs::File,
+}
+
+#[cfg(unix)]
+pub fn lock_registry() -> io::Result<RegistryLock> {
+    use std::os::unix::io::AsRawFd;
+
+    const LOCK_EX: i32 = 2;
+    extern "C" {
+        fn flock(fd: i32, operation: i32) -> i32;
+    }
+
+    let file = fs::OpenOptions::new().create(true).append(true).open(runtime_dir()?.join("windows.lock"))?;
+    if unsafe { flock(file.as_raw_fd(), LOCK_EX) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(RegistryLock { _file: file })
+}
+
+// A lock file nobody else may open while we have it
+#[cfg(windows)]
+pub fn lock_registry() -> io::Result<RegistryLock> {
+    use std::os::windows::fs::OpenOptionsExt;
+
+    const ERROR_SHARING_VIOLATION: i32 = 32;
+    let path = runtime_dir()?.join("windows.lock");
+    for _ in 0..200 {
+        match fs::OpenOptions::new().create(true).append(true).share_mode(0).open(&path) {
+            Ok(file) => return Ok(RegistryLock { _file: file }),
+            Err(e) if e.raw_os_error() == Some(ERROR_SHARING_VIOLATION) => {
+                std::thread::sleep(std::time::Duration::from_millis(10))
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Err(io::Error::new(io::ErrorKind::TimedOut, "The session registry stays locked"))
+}
+
+// Rewrite the registry with only the running sessions. The new file replaces
+// the old one in a rename; lines wrappers append to the old file meanwhile
+// are carried over, so none are lost.
+fn compact() -> io::Result<()> {
+    let _lock = lock_registry()?;
+    let path = windows_file()?;
+    let mut old = fs::File::open(&path)?;
+    let mut content = String::new();
+    old.read_to_string(&mut content)?;
+
+    let temp = path.with_extension("tsv.tmp");
+    let compacted: String = parse_sessions(&content).iter().map(format_session).collect();
+    fs::write(&temp, compacted)?;
+    fs::rename(&temp, &path)?;
+
+    // A wrapper that opened the old file just before the rename writes to it
+    // right after; look twice
+    let mut late = Vec::new();
+    old.read_to_end(&mut late)?;
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    old.read_to_end(&mut late)?;
+    if !late.is_empty() {
+        fs::OpenOptions::new().append(true).open(&path)?.write_all(&late)?;
+    }
+    Ok(())
+}
+
+pub fn register(session: &Session) -> io::Result<()> {
+    prune_exit_files();
+    let _lock = lock_registry()?;
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(windows_file()?)?;
+    file.write_all(format_session(session).as_bytes())
+}
+
+// Forget the exit codes of sessions that ended long ago
+fn prune_exit_files() {
+    let entries = match runtime_dir().and_then(fs::read_dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let old = entry
+            .metadata()
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age.as_secs() > EXIT_FILE_SECS);
+        if old && entry.file_name().to_string_lossy().starts_with("exit-") {
+            fs::remove_file(entry.path()).ok();
+        }
+    }
+}
+
+// Mark a session closed: killed, or its terminal never started
+pub fn record_close(id: &str) -> io::Result<()> {
+    fs::write(exit_file(id)?, "killed\n")?;
+    let _lock = lock_registry()?;
+    let mut file = fs::OpenOptions::new().append(true).open(windows_file()?)?;
+    writeln!(file, "close\t{}", id)
+}
+
+// How a session ended, once it has: Some(exit code), the code None when the
+// session was killed or its wrapper could not tell. The exit file says it
+// first; sessions that have none only have the close line in the registry.
+pub fn closed(id: &str) -> Option<Option<i64>> {
+    let written = exit_file(id).and_then(fs::read_to_string).unwrap_or_default();
+    // Empty while the wrapper is still writing it
+    if !written.trim().is_empty() {
+        return Some(written.trim().parse().ok());
+    }
+    let content = windows_file().and_then(fs::read_to_string).unwrap_or_default();
+    content.lines().find_map(|line| match line.split('\t').collect::<Vec<_>>()[..] {
+        ["close", closed] if closed.trim() == id => Some(None),
+        ["close", closed, code] if closed == id => Some(code.trim().parse().ok()),
+        _ => None,
+    })
+}
+
+// A session by its number in `rund ps`, its id, its title or a file it has open
+pub fn find_session<'a>(sessions: &'a [Session], target: &str) -> Option<&'a Session> {
+    if let Ok(n) = target.parse::<usize>() {
+        if n >= 1 && n <= sessions.len() {
+            return Some(&sessions[n - 1]);
+        }
+    }
+    let path = env::current_dir().ok().map(|cwd| cwd.join(target));
+    let path = path.map(|p| dunce::canonicalize(&p).unwrap_or(p));
+    sessions.iter().find(|s| s.id == target).or_else(|| {
+        sessions
+            .iter()
+            .find(|s| s.title == target)
+            .or_else(|| sessions.iter().find(|s| path.as_ref().is_some_and(|p| s.files.contains(p))))
+    })
+}
+
+// End a session: the wrapper shell's process group, which holds the command
+// too, so the terminal closes with it
+#[cfg(not(target_os = "windows"))]
+pub fn kill(session: &Session) -> io::Result<()> {
+    use std::process::{Command, Stdio};
+
+    let pid = session.pid.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, "The session has not reported its process yet")
+    })?;
+    let kill = |target: &str| {
+        Command::new("kill")
+            .args(["-TERM", "--", target])
+            .stderr(Stdio::null())
+            .status()
+            .map(|status| status.success())
+    };
+    // Not a group leader (the terminal did not start a new session): take
+    // the command down with its parent
+    if !kill(&format!("-{}", pid))? {
+        Command::new("pkill").args(["-TERM", "-P", &pid.to_string()]).status().ok();
+        if !kill(&pid.to_string())? {
+            return Err(io::Error::new(io::ErrorKind::Other, format!("Could not kill process {}", pid)));
+        }
+    }
+    record_close(&session.id)
+}
+
+#[cfg(target_os = "windows")]
+pub fn kill(session: &Session) -> io::Result<()> {
+    use std::process::{Command, Stdio};
+
+    let pid = session.pid.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, "The session has not reported its process yet")
+    })?;
+    // The console host and everything started in it
+    let status = Command::new("taskkill")
+        .args(["/PID", &pid.to_string(), "/T", "/F"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()?;
+    if !status.success() {
+        return Err(io::Error::new(io::ErrorKind::Other, format!("Could not kill process {}", pid)));
+    }
+    record_close(&session.id)
+}
+
+// Raise a session's window: tmux panes are selected in tmux, X11 windows
+// activated by the id their terminal reported or else by title
+#[cfg(target_os = "linux")]
+pub fn focus(session: &Session) -> io::Result<()> {
+    use std::process::{Command, Stdio};
+
+    let run = |program: &str, args: &[&str]| {
+        Command::new(program)
+            .args(args)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+    };
+    let done = match (session.backend.as_str(), session.window.as_deref()) {
+        ("tmux", Some(pane)) => run("tmux", &["select-window", "-t", pane]),
+        (_, Some(window)) => {
+            run("wmctrl", &["-i", "-a", window]) || run("xdotool", &["windowactivate", window])
+        }
+        (_, None) => {
+            run("wmctrl", &["-a", &session.title])
+                || run("xdotool", &["search", "--name", &format!("^{}$", session.title), "windowactivate"])
+        }
+    };
+    if done {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "Could not raise the window (needs wmctrl or xdotool on X11, or tmux)",
+        ))
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn focus(session: &Session) -> io::Result<()> {
+    let script = format!(
+        r#"tell application "Terminal"
+    activate
+    set index of (first window whose name contains "{}") to 1
+end tell"#,
+        session.title.replace('\\', "\\\\").replace('"', "\\\"")
+    );
+    let status = std::process::Command::new("osascript").arg("-e").arg(&script).status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::new(io::ErrorKind::Other, "Could not raise the Terminal window"))
+    }
+}
+
+// AppActivate takes a process id and brings its console window forward
+#[cfg(target_os = "windows")]
+pub fn focus(session: &Session) -> io::Result<()> {
+    let pid = session.pid.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, "The session has not reported its process yet")
+    })?;
+    let status = std::process::Command::new("powershell")
+        .args([
+            "-NoProfile",
+            "-Command",
+            &format!("if (-not (New-Object -ComObject WScript.Shell).AppActivate({})) {{ exit 1 }}", pid),
+        ])
+        .status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::new(io::ErrorKind::Other, "Could not raise the console window"))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    #[cfg(unix)]
+    use std::ffi::OsString;
+    #[cfg(unix)]
+    use std::path::Path;
+    #[cfg(unix)]
+    use std::sync::{Mutex, MutexGuard};
+
+    // Tests that point XDG_RUNTIME_DIR somewhere take turns
+    #[cfg(unix)]
+    static RUNTIME_DIR: Mutex<()> = Mutex::new(());
+
+    // XDG_RUNTIME_DIR pointing at a directory of the test's own while this is
+    // held; the previous value is back once it is dropped, failed test or not
+    #[cfg(unix)]
+    pub struct RuntimeDir {
+        previous: Option<OsString>,
+        _turn: MutexGuard<'static, ()>,
+    }
+
+    #[cfg(unix)]
+    pub fn runtime_dir_at(base: &Path) -> RuntimeDir {
+        let turn = RUNTIME_DIR.lock().unwrap_or_else(|e| e.into_inner());
+        let previous = env::var_os("XDG_RUNTIME_DIR");
+        fs::create_dir_all(base).unwrap();
+        env::set_var("XDG_RUNTIME_DIR", base);
+        RuntimeDir { previous, _turn: turn }
+    }
+
+    #[cfg(unix)]
+    impl Drop for RuntimeDir {
+        fn drop(&mut self) {
+            match self.previous.take() {
+                Some(dir) => env::set_var("XDG_RUNTIME_DIR", dir),
+                None => env::remove_var("XDG_RUNTIME_DIR"),
+            }
+        }
+    }
+
+    #[test]
+    fn sessions_from_the_log() {
+        let now = now_secs();
+        let log = format!(
+            "open\ta\t10\t20\t800\t600\t{now}\n\
+             session\ta\tkitty\t1\tnvim\tnotes\t/tmp/notes.md\n\
+             open\tb\t0\t0\t1\t1\t{now}\n\
+             close\tb\t0\n\
+             layout\tc\t/home/me/dev.toml\tlogs\n\
+             close\td\n\
+             open\tc\t0\t0\t1\t1\t{now}\n\
+             open\td\t0\t0\t1\t1\t{now}\n\
+             open\tstale\t0\t0\t1\t1\t{stale}\n",
+            now = now,
+            stale = now - STARTUP_GRACE_SECS - 1
+        );
+        let sessions = parse_sessions(&log);
+        let ids: Vec<&str> = sessions.iter().map(|s| s.id.as_str()).collect();
+        // Lines carried over by compaction may follow later ones: c's layout
+        // and d's close come before their open lines
+        assert_eq!(ids, ["a", "c"]);
+        let a = &sessions[0];
+        assert_eq!((a.rect.x, a.rect.width, a.backend.as_str(), a.title.as_str()), (10, 800, "kitty", "notes"));
+        assert_eq!(a.files, [PathBuf::from("/tmp/notes.md")]);
+        assert_eq!(sessions[1].layout, Some(("/home/me/dev.toml".to_string(), "logs".to_string())));
+    }
+
+    #[test]
+    fn formatted_sessions_read_back() {
+        let session = Session {
+            id: "x".to_string(),
+            rect: Rect { x: -5, y: 7, width: 640, height: 480 },
+            positioned: false,
+            backend: "tmux".to_string(),
+            app: "htop".to_string(),
+            title: "tab\there".to_string(),
+            window: Some("%3".to_string()),
+            created: now_secs(),
+            ..Session::default()
+        };
+        let parsed = parse_sessions(&format_session(&session));
+        assert_eq!(parsed.len(), 1);
+        assert_eq!((parsed[0].rect, parsed[0].positioned), (session.rect, false));
+        assert_eq!((parsed[0].title.as_str(), parsed[0].window.as_deref()), ("tab here", Some("%3")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn runtime_dir_must_be_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let base = env::temp_dir().join(format!("rund-test-runtime-{}", std::process::id()));
+        let _dir = runtime_dir_at(&base);
+        let dir = runtime_dir().unwrap();
+        assert_eq!(fs::metadata(&dir).unwrap().permissions().mode() & 0o777, 0o700);
+
+        // Readable by others, or put in place by someone else as a symlink
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(runtime_dir().unwrap_err().kind(), io::ErrorKind::PermissionDenied);
+        assert!(windows_file().is_err() && lock_registry().is_err());
+        fs::remove_dir(&dir).unwrap();
+        std::os::unix::fs::symlink(env::temp_dir(), &dir).unwrap();
+        assert!(runtime_dir().is_err());
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn exit_status_outlives_the_registry() {
+        use crate::wrapper::{sh_hooked, WindowTracking, WrapperHooks};
+
+        let base = env::temp_dir().join(format!("rund-test-exit-{}", std::process::id()));
+        let _dir = runtime_dir_at(&base);
+        let session = |id: &str| Session { id: id.to_string(), created: now_secs(), ..Session::default() };
+
+        let id = new_id();
+        register(&session(&id)).unwrap();
+        let hooks = WrapperHooks {
+            tracking: Some(WindowTracking {
+                id: id.clone(),
+                file: windows_file().unwrap(),
+                exit_file: exit_file(&id).unwrap(),
+                window_var: None,
+            }),
+            ..WrapperHooks::default()
+        };
+        let status = std::process::Command::new("sh").arg("-c").arg(sh_hooked("exit 3", &hooks)).status().unwrap();
+        assert_eq!(status.code(), Some(3));
+        let killed = new_id();
+        register(&session(&killed)).unwrap();
+        record_close(&killed).unwrap();
+
+        // With both gone from the registry, only their exit files tell
+        compact().unwrap();
+        let registry = fs::read_to_string(windows_file().unwrap()).unwrap();
+        assert!(!registry.contains(&id) && !registry.contains(&killed));
+        assert_eq!(closed(&id), Some(Some(3)));
+        assert_eq!(closed(&killed), Some(None));
+        assert_eq!(closed("never-launched"), None);
+        fs::remove_dir_all(&base).unwrap();
+    }
+}
@@ -0,0 +1,429 @@
+// src/screen.rs - Monitor queries for resolving relative geometry
+#[cfg(not(target_os = "windows"))]
+use std::process::Command;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    fn contains(&self, (px, py): (i32, i32)) -> bool {
+        px >= self.x
+            && py >= self.y
+            && px < self.x + self.width as i32
+            && py < self.y + self.height as i32
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Monitor {
+    pub name: String,
+    pub rect: Rect,
+    pub primary: bool,
+}
+
+// Which monitor a popup should land on (`monitor = ...` in config)
+#[derive(Debug, Clone, PartialEq)]
+pub enum MonitorTarget {
+    Primary,
+    // The monitor under the mouse pointer
+    Cursor,
+    // The monitor holding the focused window
+    Focused,
+    // Position in the `rund --monitors` list, counting from 0
+    Index(usize),
+    Name(String),
+}
+
+impl MonitorTarget {
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        match value.to_lowercase().as_str() {
+            "" => None,
+            "primary" => Some(MonitorTarget::Primary),
+            "cursor" | "mouse" => Some(MonitorTarget::Cursor),
+            "focused" | "active" => Some(MonitorTarget::Focused),
+            _ => Some(match value.parse() {
+                Ok(index) => MonitorTarget::Index(index),
+                Err(_) => MonitorTarget::Name(value.to_string()),
+            }),
+        }
+    }
+}
+
+// Resolve a target to a monitor rectangle. Anything that cannot be found
+// (no pointer query tool, unknown name) falls back to the primary monitor.
+pub fn select(target: Option<&MonitorTarget>) -> Option<Rect> {
+    let monitors = monitors();
+    let primary = monitors
+        .iter()
+        .find(|m| m.primary)
+        .or(monitors.first())
+        .map(|m| m.rect);
+
+    let containing = |point: Option<(i32, i32)>| {
+        point.and_then(|p| monitors.iter().find(|m| m.rect.contains(p)).map(|m| m.rect))
+    };
+
+    let chosen = match target {
+        None | Some(MonitorTarget::Primary) => None,
+        Some(MonitorTarget::Cursor) => containing(cursor_position()),
+        Some(MonitorTarget::Focused) => containing(focused_window_center()),
+        Some(MonitorTarget::Index(index)) => monitors.get(*index).map(|m| m.rect),
+        Some(MonitorTarget::Name(name)) => monitors
+            .iter()
+            .find(|m| m.name.eq_ignore_ascii_case(name) || m.name.to_lowercase().ends_with(&name.to_lowercase()))
+            .map(|m| m.rect),
+    };
+
+    chosen.or(primary).or_else(fallback_screen)
+}
+
+// Parse "1920x1080+0+0" as printed by xrandr
+#[cfg(target_os = "linux")]
+fn parse_xrandr_rect(token: &str) -> Option<Rect> {
+    let (size, offset) = token.split_once('+')?;
+    let (width, height) = size.split_once('x')?;
+    let (x, y) = offset.split_once('+')?;
+    Some(Rect {
+        x: x.parse().ok()?,
+        y: y.parse().ok()?,
+        width: width.parse().ok()?,
+        height: height.parse().ok()?,
+    })
+}
+
+// Values from `xdotool ... --shell` output such as "X=120"
+#[cfg(target_os = "linux")]
+fn shell_values(output: &[u8]) -> std::collections::HashMap<String, i32> {
+    String::from_utf8_lossy(output)
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .filter_map(|(key, value)| Some((key.to_string(), value.trim().parse().ok()?)))
+        .collect()
+}
+
+// Connected outputs in xrandr order; xrandr also answers under XWayland
+#[cfg(target_os = "linux")]
+pub fn monitors() -> Vec<Monitor> {
+    let output = match Command::new("xrandr").arg("--current").output() {
+        Ok(output) => output,
+        Err(_) => return Vec::new(),
+    };
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| line.contains(" connected"))
+        .filter_map(|line| {
+            Some(Monitor {
+                name: line.split_whitespace().next()?.to_string(),
+                rect: line.split_whitespace().find_map(parse_xrandr_rect)?,
+                primary: line.contains(" primary "),
+            })
+        })
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn cursor_position() -> Option<(i32, i32)> {
+    let output = Command::new("xdotool").args(["getmouselocation", "--shell"]).output().ok()?;
+    let values = shell_values(&output.stdout);
+    Some((*values.get("X")?, *values.get("Y")?))
+}
+
+#[cfg(target_os = "linux")]
+fn focused_window_center() -> Option<(i32, i32)> {
+    let output = Command::new("xdotool")
+        .args(["getactivewindow", "getwindowgeometry", "--shell"])
+        .output()
+        .ok()?;
+    let values = shell_values(&output.stdout);
+    Some((
+        values.get("X")? + values.get("WIDTH")? / 2,
+        values.get("Y")? + values.get("HEIGHT")? / 2,
+    ))
+}
+
+// The terminal window this process runs in, found through the WINDOWID the
+// emulator exports; xwininfo covers systems without xdotool
+#[cfg(target_os = "linux")]
+pub fn current_window() -> Option<Rect> {
+    let id = std::env::var("WINDOWID").ok().filter(|id| !id.is_empty())?;
+
+    if let Ok(output) = Command::new("xdotool").args(["getwindowgeometry", "--shell", &id]).output() {
+        let values = shell_values(&output.stdout);
+        if let (Some(&x), Some(&y), Some(&w), Some(&h)) =
+            (values.get("X"), values.get("Y"), values.get("WIDTH"), values.get("HEIGHT"))
+        {
+            return Some(Rect { x, y, width: w.max(0) as u32, height: h.max(0) as u32 });
+        }
+    }
+
+    // "  Absolute upper-left X:  100" / "  Width: 800"
+    let output = Command::new("xwininfo").args(["-id", &id]).output().ok()?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let value = |label: &str| {
+        text.lines()
+            .find_map(|line| line.trim().strip_prefix(label))
+            .and_then(|v| v.trim().parse::<i32>().ok())
+    };
+    Some(Rect {
+        x: value("Absolute upper-left X:")?,
+        y: value("Absolute upper-left Y:")?,
+        width: value("Width:")?.max(0) as u32,
+        height: value("Height:")?.max(0) as u32,
+    })
+}
+
+// Without xrandr, xdpyinfo still knows the size of the whole X screen:
+// "  dimensions:    1920x1080 pixels (508x285 millimeters)"
+#[cfg(target_os = "linux")]
+fn fallback_screen() -> Option<Rect> {
+    let output = Command::new("xdpyinfo").output().ok()?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let line = text.lines().find(|l| l.trim_start().starts_with("dimensions:"))?;
+    let (width, height) = line.split_whitespace().nth(1)?.split_once('x')?;
+    Some(Rect {
+        x: 0,
+        y: 0,
+        width: width.parse().ok()?,
+        height: height.parse().ok()?,
+    })
+}
+
+// NSScreen frames via JXA, one "x,y,width,height,name" line per screen. Cocoa
+// puts the origin bottom-left, so rows are flipped against the first screen.
+#[cfg(target_os = "macos")]
+const JXA_SCREENS: &str = r#"ObjC.import('AppKit');
+var s = $.NSScreen.screens, out = [];
+for (var i = 0; i < s.count; i++) {
+    var f = s.objectAtIndex(i).frame;
+    out.push([f.origin.x, f.origin.y, f.size.width, f.size.height, s.objectAtIndex(i).localizedName.js].join(','));
+}
+out.join('\n')"#;
+
+#[cfg(target_os = "macos")]
+fn jxa(script: &str) -> Option<String> {
+    let output = Command::new("osascript")
+        .args(["-l", "JavaScript", "-e", script])
+        .output()
+        .ok()?;
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(target_os = "macos")]
+pub fn monitors() -> Vec<Monitor> {
+    let text = jxa(JXA_SCREENS).unwrap_or_default();
+    let mut frames = Vec::new();
+    for line in text.lines() {
+        let mut parts = line.splitn(5, ',');
+        let mut number = || parts.next().and_then(|v| v.trim().parse::<f64>().ok());
+        if let (Some(x), Some(y), Some(w), Some(h)) = (number(), number(), number(), number()) {
+            frames.push((x, y, w, h, parts.next().unwrap_or("").to_string()));
+        }
+    }
+
+    let main_height = frames.first().map(|f| f.3).unwrap_or(0.0);
+    frames
+        .into_iter()
+        .enumerate()
+        .map(|(i, (x, y, w, h, name))| Monitor {
+            name,
+            rect: Rect {
+                x: x as i32,
+                y: (main_height - y - h) as i32,
+                width: w as u32,
+                height: h as u32,
+            },
+            primary: i == 0,
+        })
+        .collect()
+}
+
+#[cfg(target_os = "macos")]
+fn cursor_position() -> Option<(i32, i32)> {
+    let text = jxa(
+        "ObjC.import('AppKit'); var p = $.NSEvent.mouseLocation; \
+         [p.x, $.NSScreen.screens.objectAtIndex(0).frame.size.height - p.y].join(',')",
+    )?;
+    let (x, y) = text.trim().split_once(',')?;
+    Some((x.parse::<f64>().ok()? as i32, y.parse::<f64>().ok()? as i32))
+}
+
+// Terminal.app cannot see other apps' windows, so "focused" follows the pointer
+#[cfg(target_os = "macos")]
+fn focused_window_center() -> Option<(i32, i32)> {
+    cursor_position()
+}
+
+#[cfg(target_os = "macos")]
+fn fallback_screen() -> Option<Rect> {
+    None
+}
+
+// Terminal.app keeps the window running a script in front, and its bounds
+// are already top-left based: "left, top, right, bottom"
+#[cfg(target_os = "macos")]
+pub fn current_window() -> Option<Rect> {
+    let output = Command::new("osascript")
+        .args(["-e", "tell application \"Terminal\" to get bounds of front window"])
+        .output()
+        .ok()?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let bounds: Vec<i32> = text.split(',').filter_map(|v| v.trim().parse().ok()).collect();
+    match bounds[..] {
+        [left, top, right, bottom] => Some(Rect {
+            x: left,
+            y: top,
+            width: (right - left).max(0) as u32,
+            height: (bottom - top).max(0) as u32,
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod win {
+    use super::{Monitor, Rect};
+    use std::ffi::c_void;
+
+    type HMONITOR = *mut c_void;
+    type HDC = *mut c_void;
+    type HWND = *mut c_void;
+    type BOOL = i32;
+
+    #[repr(C)]
+    #[derive(Default)]
+    pub struct RECT {
+        pub left: i32,
+        pub top: i32,
+        pub right: i32,
+        pub bottom: i32,
+    }
+
+    #[repr(C)]
+    #[derive(Default)]
+    pub struct POINT {
+        pub x: i32,
+        pub y: i32,
+    }
+
+    #[repr(C)]
+    struct MONITORINFOEXW {
+        cb_size: u32,
+        rc_monitor: RECT,
+        rc_work: RECT,
+        dw_flags: u32,
+        sz_device: [u16; 32],
+    }
+
+    const MONITORINFOF_PRIMARY: u32 = 1;
+
+    type MonitorEnumProc = unsafe extern "system" fn(HMONITOR, HDC, *mut RECT, isize) -> BOOL;
+
+    #[link(name = "user32")]
+    extern "system" {
+        fn EnumDisplayMonitors(hdc: HDC, clip: *const RECT, callback: MonitorEnumProc, data: isize) -> BOOL;
+        fn GetMonitorInfoW(monitor: HMONITOR, info: *mut MONITORINFOEXW) -> BOOL;
+        pub fn GetCursorPos(point: *mut POINT) -> BOOL;
+        pub fn GetForegroundWindow() -> HWND;
+        pub fn GetWindowRect(hwnd: HWND, rect: *mut RECT) -> BOOL;
+        pub fn IsWindowVisible(hwnd: HWND) -> BOOL;
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        pub fn GetConsoleWindow() -> HWND;
+    }
+
+    pub fn to_rect(rect: &RECT) -> Rect {
+        Rect {
+            x: rect.left,
+            y: rect.top,
+            width: (rect.right - rect.left).max(0) as u32,
+            height: (rect.bottom - rect.top).max(0) as u32,
+        }
+    }
+
+    unsafe extern "system" fn collect(monitor: HMONITOR, _hdc: HDC, _clip: *mut RECT, data: isize) -> BOOL {
+        let monitors = &mut *(data as *mut Vec<Monitor>);
+        let mut info: MONITORINFOEXW = std::mem::zeroed();
+        info.cb_size = std::mem::size_of::<MONITORINFOEXW>() as u32;
+
+        if GetMonitorInfoW(monitor, &mut info) != 0 {
+            let len = info.sz_device.iter().position(|&c| c == 0).unwrap_or(info.sz_device.len());
+            monitors.push(Monitor {
+                // "\\.\DISPLAY1" -> "DISPLAY1"
+                name: String::from_utf16_lossy(&info.sz_device[..len])
+                    .trim_start_matches(['\\', '.'])
+                    .to_string(),
+                rect: to_rect(&info.rc_monitor),
+                primary: info.dw_flags & MONITORINFOF_PRIMARY != 0,
+            });
+        }
+        1
+    }
+
+    pub fn monitors() -> Vec<Monitor> {
+        let mut monitors: Vec<Monitor> = Vec::new();
+        unsafe {
+            EnumDisplayMonitors(
+                std::ptr::null_mut(),
+                std::ptr::null(),
+                collect,
+                &mut monitors as *mut Vec<Monitor> as isize,
+            );
+        }
+        monitors
+    }
+}
+
+#[cfg(target_os = "windows")]
+pub fn monitors() -> Vec<Monitor> {
+    win::monitors()
+}
+
+#[cfg(target_os = "windows")]
+fn cursor_position() -> Option<(i32, i32)> {
+    let mut point = win::POINT::default();
+    if unsafe { win::GetCursorPos(&mut point) } == 0 {
+        return None;
+    }
+    Some((point.x, point.y))
+}
+
+#[cfg(target_os = "windows")]
+fn focused_window_center() -> Option<(i32, i32)> {
+    let mut rect = win::RECT::default();
+    unsafe {
+        let hwnd = win::GetForegroundWindow();
+        if hwnd.is_null() || win::GetWindowRect(hwnd, &mut rect) == 0 {
+            return None;
+        }
+    }
+    let rect = win::to_rect(&rect);
+    Some((rect.x + rect.width as i32 / 2, rect.y + rect.height as i32 / 2))
+}
+
+#[cfg(target_os = "windows")]
+fn fallback_screen() -> Option<Rect> {
+    None
+}
+
+// The console window this process is attached to. Under Windows Terminal that
+// is a hidden pseudo console whose rect means nothing, so it is skipped.
+#[cfg(target_os = "windows")]
+pub fn current_window() -> Option<Rect> {
+    let mut rect = win::RECT::default();
+    unsafe {
+        let hwnd = win::GetConsoleWindow();
+        if hwnd.is_null() || win::IsWindowVisible(hwnd) == 0 || win::GetWindowRect(hwnd, &mut rect) == 0 {
+            return None;
+        }
+    }
+    Some(win::to_rect(&rect))
+}
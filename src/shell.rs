@@ -0,0 +1,215 @@
+// src/shell.rs - Shells a popup's command can run in
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShellKind {
+    Sh,
+    Bash,
+    Zsh,
+    Fish,
+    Pwsh,
+    Nu,
+    Cmd,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shell {
+    pub kind: ShellKind,
+    // What gets executed: a name looked up on PATH or a full path
+    pub program: String,
+    pub login: bool,
+    pub interactive: bool,
+}
+
+impl Default for Shell {
+    #[cfg(target_os = "windows")]
+    fn default() -> Self {
+        Shell::new(ShellKind::Cmd, "cmd")
+    }
+
+    // bash as always, plain sh on systems that do not have it
+    #[cfg(not(target_os = "windows"))]
+    fn default() -> Self {
+        let has_bash = std::env::var_os("PATH")
+            .is_some_and(|path| std::env::split_paths(&path).any(|dir| dir.join("bash").is_file()));
+        if has_bash {
+            Shell::new(ShellKind::Bash, "bash")
+        } else {
+            Shell::new(ShellKind::Sh, "sh")
+        }
+    }
+}
+
+impl Shell {
+    fn new(kind: ShellKind, program: &str) -> Self {
+        Shell {
+            kind,
+            program: program.to_string(),
+            login: false,
+            interactive: false,
+        }
+    }
+
+    // "fish", "/usr/local/bin/zsh", "pwsh.exe", ...; the kind comes from the
+    // file name, so full paths to any of the supported shells work
+    pub fn parse(value: &str) -> Option<Self> {
+        let name = Path::new(value).file_stem()?.to_str()?.to_lowercase();
+        let kind = match name.as_str() {
+            "sh" | "dash" | "ash" | "ksh" => ShellKind::Sh,
+            "bash" => ShellKind::Bash,
+            "zsh" => ShellKind::Zsh,
+            "fish" => ShellKind::Fish,
+            "pwsh" | "powershell" => ShellKind::Pwsh,
+            "nu" | "nushell" => ShellKind::Nu,
+            "cmd" => ShellKind::Cmd,
+            _ => return None,
+        };
+        Some(Shell::new(kind, if name == "nushell" { "nu" } else { value }))
+    }
+
+    // Whether rund's generated wrapper can run in this shell as is: POSIX sh
+    // syntax on Unix, batch syntax on Windows
+    pub fn runs_wrapper(&self) -> bool {
+        if cfg!(target_os = "windows") {
+            self.kind == ShellKind::Cmd
+        } else {
+            matches!(self.kind, ShellKind::Sh | ShellKind::Bash | ShellKind::Zsh)
+        }
+    }
+
+    // The shell that runs the wrapper: this one, or the platform's own
+    pub fn host(&self) -> Shell {
+        if self.runs_wrapper() {
+            self.clone()
+        } else if cfg!(target_os = "windows") {
+            Shell::new(ShellKind::Cmd, "cmd")
+        } else {
+            Shell::new(ShellKind::Sh, "sh")
+        }
+    }
+
+    // Program and arguments that make this shell run `script`
+    pub fn argv(&self, script: &str) -> Vec<String> {
+        let mut argv = vec![self.program.clone()];
+        match self.kind {
+            ShellKind::Pwsh => {
+                // -Login is only honored as the very first argument
+                if self.login {
+                    argv.push("-Login".to_string());
+                }
+                argv.push("-NoLogo".to_string());
+                if !self.interactive {
+                    argv.push("-NoProfile".to_string());
+                }
+                argv.push("-Command".to_string());
+            }
+            // AutoRun is cmd's startup script, skipped unless interactive
+            ShellKind::Cmd => {
+                if !self.interactive {
+                    argv.push("/D".to_string());
+                }
+                argv.push("/C".to_string());
+            }
+            _ => {
+                if self.login {
+                    argv.push("-l".to_string());
+                }
+                if self.interactive {
+                    argv.push("-i".to_string());
+                }
+                argv.push("-c".to_string());
+            }
+        }
+        argv.push(script.to_string());
+        argv
+    }
+
+    // `value` as one literal word in this shell's syntax
+    pub fn quote(&self, value: &str) -> String {
+        match self.kind {
+            ShellKind::Sh | ShellKind::Bash | ShellKind::Zsh => format!("'{}'", value.replace('\'', "'\\''")),
+            ShellKind::Fish => format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'")),
+            ShellKind::Pwsh => format!("'{}'", value.replace('\'', "''")),
+            // Raw strings take anything but their own closing delimiter
+            ShellKind::Nu if value.contains('\'') => format!("r#'{}'#", value),
+            ShellKind::Nu => format!("'{}'", value),
+            // cmd.exe has no backslash escape; programs read "" inside a
+            // quoted argument as a literal quote
+            ShellKind::Cmd => format!("\"{}\"", value.replace('"', "\"\"")),
+        }
+    }
+
+    // `value` as one argument: as is when no shell gives any of its
+    // characters a meaning, quoted otherwise
+    pub fn arg(&self, value: &str) -> String {
+        let plain = |c: char| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '/' | ':' | '=' | '+');
+        if !value.is_empty() && value.chars().all(plain) {
+            value.to_string()
+        } else {
+            self.quote(value)
+        }
+    }
+
+    // The command line the wrapper runs for `command`: the command itself
+    // when the wrapper already runs in this shell, otherwise a call to it
+    pub fn command(&self, command: &str) -> String {
+        if self.runs_wrapper() {
+            return command.to_string();
+        }
+        let host = self.host();
+        self.argv(command)
+            .iter()
+            .map(|arg| host.quote(arg))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shell(kind: ShellKind) -> Shell {
+        Shell::new(kind, "shell")
+    }
+
+    #[test]
+    fn quote_each_kind() {
+        let cases = [
+            (ShellKind::Sh, "it's $HOME", r"'it'\''s $HOME'"),
+            (ShellKind::Bash, "a \"b\" `c`", "'a \"b\" `c`'"),
+            (ShellKind::Zsh, "", "''"),
+            (ShellKind::Fish, r"it's a\b", r"'it\'s a\\b'"),
+            (ShellKind::Pwsh, "it's $env:HOME", "'it''s $env:HOME'"),
+            (ShellKind::Nu, "plain $x", "'plain $x'"),
+            (ShellKind::Nu, "it's", "r#'it's'#"),
+            (ShellKind::Cmd, r#"say "hi" & exit"#, r#""say ""hi"" & exit""#),
+            (ShellKind::Cmd, r"C:\Program Files\x", r#""C:\Program Files\x""#),
+        ];
+        for (kind, value, expected) in cases {
+            assert_eq!(shell(kind).quote(value), expected, "{:?} quoting {:?}", kind, value);
+        }
+    }
+
+    // What sh makes of the quoted word is the value itself
+    #[cfg(unix)]
+    #[test]
+    fn quote_round_trips_through_sh() {
+        for value in ["it's", "$HOME `id` \"x\"", "a\\b\nc", "*", ""] {
+            let script = format!("printf %s {}", shell(ShellKind::Sh).quote(value));
+            let output = std::process::Command::new("sh").args(["-c", &script]).output().unwrap();
+            assert_eq!(String::from_utf8_lossy(&output.stdout), value);
+        }
+    }
+
+    #[test]
+    fn command_calls_other_shells_from_the_wrapper() {
+        let fish = Shell::parse("fish").unwrap();
+        if cfg!(target_os = "windows") {
+            assert_eq!(fish.command("echo hi"), r#""fish" "-c" "echo hi""#);
+        } else {
+            assert_eq!(fish.command("echo hi"), "'fish' '-c' 'echo hi'");
+            assert_eq!(Shell::parse("/bin/zsh").unwrap().command("echo hi"), "echo hi");
+        }
+    }
+}
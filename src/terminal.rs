@@ -0,0 +1,576 @@
+// src/terminal.rs - The terminal backends a popup opens in: emulators on
+// Linux (or a tmux window), Terminal.app on macOS, and cmd or Windows
+// Terminal on Windows
+#[cfg(target_os = "linux")]
+use std::env;
+use std::io;
+use std::path::Path;
+
+#[cfg(target_os = "windows")]
+use crate::config::TerminalType;
+use crate::config::{AppGeometry, Config};
+use crate::launch::RunOptions;
+#[cfg(not(target_os = "windows"))]
+use crate::log;
+#[cfg(not(target_os = "windows"))]
+use crate::shell::Shell;
+#[cfg(not(target_os = "windows"))]
+use crate::wrapper::{sh_hooked, sh_wrapper};
+#[cfg(target_os = "macos")]
+use crate::wrapper::sh_quote;
+use crate::wrapper::{PausePlan, WrapperHooks};
+
+// Terminal emulators tried on Linux, in order of preference
+#[cfg(target_os = "linux")]
+pub(crate) const LINUX_TERMINALS: [&str; 5] = ["alacritty", "kitty", "gnome-terminal", "konsole", "xterm"];
+
+// Backend a launch will most likely use, for its cell metrics. On Linux that
+// is the --terminal choice or the first emulator found on PATH.
+pub(crate) fn backend_name(config: &Config, options: &RunOptions) -> String {
+    #[cfg(target_os = "windows")]
+    {
+        let terminal = options.terminal.as_deref().map(TerminalType::parse).unwrap_or(config.terminal);
+        match terminal {
+            TerminalType::Cmd => "cmd".to_string(),
+            TerminalType::WindowsTerminal => "wt".to_string(),
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let _ = (config, options);
+        "terminal".to_string()
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let _ = config;
+        if let Some(ref wanted) = options.terminal {
+            return wanted.clone();
+        }
+        let path = options.var("PATH").unwrap_or_default();
+        LINUX_TERMINALS
+            .iter()
+            .find(|term| env::split_paths(&path).any(|dir| dir.join(term).is_file()))
+            .unwrap_or(&LINUX_TERMINALS[0])
+            .to_string()
+    }
+}
+
+// Everything a backend needs to open one popup: the wrapped command, where
+// and how big, and the directory and shell it runs in (cmd on Windows)
+pub(crate) struct Popup<'a> {
+    pub(crate) command: &'a str,
+    pub(crate) pause: &'a PausePlan,
+    pub(crate) hooks: &'a WrapperHooks,
+    pub(crate) geom: &'a AppGeometry,
+    pub(crate) cwd: &'a Path,
+    #[cfg(not(target_os = "windows"))]
+    pub(crate) shell: Option<&'a Shell>,
+}
+
+// Open the popup in the platform's terminal
+pub(crate) fn open(popup: &Popup, config: &Config, options: &RunOptions) -> io::Result<()> {
+    let geom = popup.geom;
+
+    #[cfg(target_os = "windows")]
+    {
+        let (cols, rows) = geom.cells(config.cell_size(&backend_name(config, options)));
+        windows::run(
+            popup.command,
+            geom.x,
+            geom.y,
+            cols,
+            rows,
+            geom.auto_position,
+            options.terminal.as_deref().map(TerminalType::parse).unwrap_or(config.terminal),
+            popup.pause,
+            popup.hooks,
+            options.title.as_deref(),
+            popup.cwd,
+        )
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let cmd_with_pause = sh_hooked(&sh_wrapper(popup.command, popup.pause), popup.hooks);
+        // Terminal.app types the script into the user's own shell, unless a
+        // shell was configured to replace it
+        let typed = match popup.shell {
+            Some(shell) => {
+                let argv = shell.host().argv(&cmd_with_pause);
+                format!("exec {}", argv.iter().map(|arg| sh_quote(arg)).collect::<Vec<_>>().join(" "))
+            }
+            None => format!("{}; exit", cmd_with_pause),
+        };
+        let typed = format!("cd {} && {}", sh_quote(&popup.cwd.display().to_string()), typed);
+        let (width, height) = geom.pixels(config.cell_size("terminal"));
+        let script = format!(
+            r#"tell application "Terminal"
+    activate
+    do script "{}"
+    set bounds of front window to {{{}, {}, {}, {}}}
+    set custom title of selected tab of front window to "{}"
+end tell"#,
+            typed.replace('\\', "\\\\").replace('"', "\\\""),
+            geom.x,
+            geom.y,
+            geom.x + width as i32,
+            geom.y + height as i32,
+            options.title.as_deref().unwrap_or("rund").replace('\\', "\\\\").replace('"', "\\\"")
+        );
+
+        log::info!("running: osascript -e {}", script);
+        reap(options.command("osascript").arg("-e").arg(&script).spawn()?);
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let cmd_with_pause = sh_hooked(&sh_wrapper(popup.command, popup.pause), popup.hooks);
+        let shell_argv = popup.shell.cloned().unwrap_or_default().host().argv(&cmd_with_pause);
+        let shell_argv: Vec<&str> = shell_argv.iter().map(String::as_str).collect();
+
+        // Each emulator renders its own font, so convert with its own cell size
+        let (alacritty_cols, alacritty_rows) = geom.cells(config.cell_size("alacritty"));
+        let alacritty_cols = format!("window.dimensions.columns={}", alacritty_cols);
+        let alacritty_rows = format!("window.dimensions.lines={}", alacritty_rows);
+        let alacritty_x = format!("window.position.x={}", geom.x);
+        let alacritty_y = format!("window.position.y={}", geom.y);
+        let (kitty_cols, kitty_rows) = geom.cells(config.cell_size("kitty"));
+        let kitty_width = format!("initial_window_width={}c", kitty_cols);
+        let kitty_height = format!("initial_window_height={}c", kitty_rows);
+        // X geometry strings for gnome-terminal and xterm: COLSxROWS[+X+Y]
+        let x_geometry = |backend: &str| {
+            let (cols, rows) = geom.cells(config.cell_size(backend));
+            if geom.auto_position {
+                format!("{}x{}", cols, rows)
+            } else {
+                format!("{}x{}{:+}{:+}", cols, rows, geom.x, geom.y)
+            }
+        };
+        let gnome_geometry = format!("--geometry={}", x_geometry("gnome-terminal"));
+        let xterm_geometry = x_geometry("xterm");
+        let title = options.title.as_deref().unwrap_or("rund");
+        let konsole_title = format!("tabtitle={}", title);
+        // xterm has no option for it and simply keeps the cwd it is spawned in
+        let cwd_arg = popup.cwd.display().to_string();
+        let gnome_cwd = format!("--working-directory={}", cwd_arg);
+
+        let terminals = [
+            (
+                "alacritty",
+                vec![
+                    "--option",
+                    &alacritty_cols,
+                    "--option",
+                    &alacritty_rows,
+                    "--option",
+                    &alacritty_x,
+                    "--option",
+                    &alacritty_y,
+                    "--title",
+                    title,
+                    "--working-directory",
+                    &cwd_arg,
+                    "-e",
+                ],
+            ),
+            (
+                "kitty",
+                vec![
+                    "-o",
+                    &kitty_width,
+                    "-o",
+                    &kitty_height,
+                    "--title",
+                    title,
+                    "--directory",
+                    &cwd_arg,
+                ],
+            ),
+            ("gnome-terminal", vec![&gnome_geometry, &gnome_cwd, "--title", title, "--"]),
+            ("konsole", vec!["-p", &konsole_title, "--workdir", &cwd_arg, "-e"]),
+            ("xterm", vec!["-geometry", &xterm_geometry, "-T", title, "-e"]),
+            // A window in the running tmux server; only used when asked for
+            ("tmux", vec!["new-window", "-n", title, "-c", &cwd_arg, "--"]),
+        ];
+
+        // --terminal picks one emulator instead of the first one that starts
+        if let Some(ref wanted) = options.terminal {
+            if !terminals.iter().any(|(term, _)| term == wanted) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Unsupported terminal '{}'. Use: alacritty, kitty, gnome-terminal, konsole, xterm, or tmux", wanted),
+                ));
+            }
+        }
+
+        let mut last_error = None;
+        for (term, args) in &terminals {
+            // tmux only when asked for; otherwise the first emulator that starts
+            let skip = match options.terminal.as_deref() {
+                Some(wanted) => wanted != *term,
+                None => !LINUX_TERMINALS.contains(term),
+            };
+            if skip {
+                log::trace!("{} skipped", term);
+                continue;
+            }
+            let argv: Vec<&str> = [*term].iter().chain(args).chain(&shell_argv).copied().collect();
+            match options.command(term).args(args).args(&shell_argv).current_dir(popup.cwd).spawn() {
+                Ok(child) => {
+                    log::info!("running (pid {}): {:?}", child.id(), argv);
+                    reap(child);
+                    return Ok(());
+                }
+                Err(e) => {
+                    log::info!("{} could not be started: {}", term, e);
+                    log::debug!("tried: {:?}", argv);
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            match (&options.terminal, last_error) {
+                (Some(ref wanted), Some(e)) => format!("Terminal '{}' could not be started: {}", wanted, e),
+                (Some(ref wanted), None) => format!("Terminal '{}' could not be started", wanted),
+                (None, _) => "No supported terminal found. Please install: alacritty, kitty, gnome-terminal, konsole, or xterm".to_string(),
+            },
+        ))
+    }
+}
+
+// Wait for a spawned terminal in the background, so a long-running daemon
+// does not collect zombies; a one-off rund simply exits before it is done
+#[cfg(not(target_os = "windows"))]
+pub(crate) fn reap(mut child: std::process::Child) {
+    std::thread::spawn(move || child.wait());
+}
+
+#[cfg(target_os = "windows")]
+pub(crate) mod windows {
+    use crate::config::TerminalType;
+    use crate::wrapper::{cmd_wrapper, PausePlan, WrapperHooks};
+    use std::path::Path;
+    use std::ffi::OsStr;
+    use std::io;
+    use std::iter::once;
+    use std::os::windows::ffi::OsStrExt;
+    use std::ptr;
+
+    type HWND = *mut std::ffi::c_void;
+    type LPCWSTR = *const u16;
+    type UINT = u32;
+    type HANDLE = *mut std::ffi::c_void;
+    type DWORD = u32;
+    type LPVOID = *mut std::ffi::c_void;
+    type BOOL = i32;
+    type WORD = u16;
+    type LPWSTR = *mut u16;
+    type HKEY = *mut std::ffi::c_void;
+    type LPCSTR = *const u8;
+    type LPDWORD = *mut DWORD;
+    type LPBYTE = *mut u8;
+    type LONG = i32;
+    type REGSAM = u32;
+
+    #[repr(C)]
+    struct STARTUPINFOW {
+        cb: DWORD,
+        lp_reserved: LPWSTR,
+        lp_desktop: LPWSTR,
+        lp_title: LPWSTR,
+        dw_x: DWORD,
+        dw_y: DWORD,
+        dw_x_size: DWORD,
+        dw_y_size: DWORD,
+        dw_x_count_chars: DWORD,
+        dw_y_count_chars: DWORD,
+        dw_fill_attribute: DWORD,
+        dw_flags: DWORD,
+        w_show_window: WORD,
+        cb_reserved2: WORD,
+        lp_reserved2: *mut u8,
+        h_std_input: HANDLE,
+        h_std_output: HANDLE,
+        h_std_error: HANDLE,
+    }
+
+    #[repr(C)]
+    struct PROCESS_INFORMATION {
+        h_process: HANDLE,
+        h_thread: HANDLE,
+        dw_process_id: DWORD,
+        dw_thread_id: DWORD,
+    }
+
+    const CREATE_NEW_CONSOLE: DWORD = 0x00000010;
+    const MB_OK: UINT = 0x00000000;
+    const MB_ICONERROR: UINT = 0x00000010;
+    const MB_TASKMODAL: UINT = 0x00002000;
+    
+    const HKEY_CURRENT_USER: HKEY = 0x80000001 as HKEY;
+    const KEY_WRITE: REGSAM = 0x20006;
+    const REG_DWORD: DWORD = 4;
+
+    #[link(name = "user32")]
+    extern "system" {
+        fn MessageBoxW(hwnd: HWND, text: LPCWSTR, caption: LPCWSTR, utype: UINT) -> i32;
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn CreateProcessW(
+            application_name: LPCWSTR,
+            command_line: LPWSTR,
+            process_attributes: LPVOID,
+            thread_attributes: LPVOID,
+            inherit_handles: BOOL,
+            creation_flags: DWORD,
+            environment: LPVOID,
+            current_directory: LPCWSTR,
+            startup_info: *mut STARTUPINFOW,
+            process_information: *mut PROCESS_INFORMATION,
+        ) -> BOOL;
+        fn CloseHandle(object: HANDLE) -> BOOL;
+    }
+
+    #[link(name = "advapi32")]
+    extern "system" {
+        fn RegCreateKeyExA(
+            hkey: HKEY,
+            lpsubkey: LPCSTR,
+            reserved: DWORD,
+            lpclass: LPWSTR,
+            dwoptions: DWORD,
+            samdesired: REGSAM,
+            lpsecurityattributes: LPVOID,
+            phkresult: *mut HKEY,
+            lpdwdisposition: LPDWORD,
+        ) -> LONG;
+        fn RegSetValueExA(
+            hkey: HKEY,
+            lpvaluename: LPCSTR,
+            reserved: DWORD,
+            dwtype: DWORD,
+            lpdata: LPBYTE,
+            cbdata: DWORD,
+        ) -> LONG;
+        fn RegDeleteValueA(hkey: HKEY, lpvaluename: LPCSTR) -> LONG;
+        fn RegCloseKey(hkey: HKEY) -> LONG;
+    }
+
+    fn to_wide_string(s: &str) -> Vec<u16> {
+        OsStr::new(s).encode_wide().chain(once(0)).collect()
+    }
+
+    pub fn show_error_centered(msg: &str) {
+        let title = to_wide_string("rund - Error");
+        let text = to_wide_string(msg);
+
+        unsafe {
+            MessageBoxW(
+                ptr::null_mut(),
+                text.as_ptr(),
+                title.as_ptr(),
+                MB_OK | MB_ICONERROR | MB_TASKMODAL,
+            );
+        }
+    }
+
+    fn set_console_registry_by_title(title: &str, x: i32, y: i32, cols: u32, rows: u32, auto_pos: bool) -> io::Result<()> {
+        unsafe {
+            let key_path_str = format!("Console\\{}\0", title);
+            let key_path = key_path_str.as_bytes();
+            let mut hkey: HKEY = ptr::null_mut();
+            let mut disposition: DWORD = 0;
+
+            let result = RegCreateKeyExA(
+                HKEY_CURRENT_USER,
+                key_path.as_ptr(),
+                0,
+                ptr::null_mut(),
+                0,
+                KEY_WRITE,
+                ptr::null_mut(),
+                &mut hkey,
+                &mut disposition,
+            );
+
+            if result != 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    format!("Failed to create registry key: {}", result),
+                ));
+            }
+
+            if !auto_pos {
+                let pos: DWORD = ((y as DWORD) << 16) | (x as DWORD & 0xFFFF);
+                RegSetValueExA(
+                    hkey,
+                    b"WindowPosition\0".as_ptr(),
+                    0,
+                    REG_DWORD,
+                    &pos as *const DWORD as LPBYTE,
+                    4,
+                );
+
+                let size: DWORD = (rows << 16) | cols;
+                RegSetValueExA(
+                    hkey,
+                    b"WindowSize\0".as_ptr(),
+                    0,
+                    REG_DWORD,
+                    &size as *const DWORD as LPBYTE,
+                    4,
+                );
+            } else {
+                RegDeleteValueA(hkey, b"WindowPosition\0".as_ptr());
+                RegDeleteValueA(hkey, b"WindowSize\0".as_ptr());
+            }
+
+            RegCloseKey(hkey);
+        }
+
+        Ok(())
+    }
+
+    pub fn run(
+        command: &str,
+        x: i32,
+        y: i32,
+        cols: u32,
+        rows: u32,
+        auto_position: bool,
+        terminal_type: TerminalType,
+        pause: &PausePlan,
+        hooks: &WrapperHooks,
+        title: Option<&str>,
+        cwd: &Path,
+    ) -> io::Result<()> {
+        let cmd_to_run = cmd_wrapper(command, pause, hooks)?;
+
+        match terminal_type {
+            TerminalType::Cmd => run_cmd_direct(&cmd_to_run, x, y, cols, rows, auto_position, title, cwd),
+            TerminalType::WindowsTerminal => run_wt(&cmd_to_run, x, y, cols, rows, auto_position, title, cwd),
+        }
+    }
+
+    fn run_cmd_direct(
+        cmd_to_run: &str,
+        x: i32,
+        y: i32,
+        cols: u32,
+        rows: u32,
+        auto_position: bool,
+        title: Option<&str>,
+        cwd: &Path,
+    ) -> io::Result<()> {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let window_title = match title {
+            // `title` takes the rest of the line, so only cmd's operators need escaping
+            Some(title) => title
+                .chars()
+                .flat_map(|c| match c {
+                    '&' | '|' | '<' | '>' | '^' => vec!['^', c],
+                    _ => vec![c],
+                })
+                .collect(),
+            None => format!("rund_{}", timestamp),
+        };
+
+        set_console_registry_by_title(&window_title, x, y, cols, rows, auto_position)?;
+
+        let cmd_path = to_wide_string("C:\\Windows\\System32\\cmd.exe");
+        
+        // PERBAIKAN CRITICAL:
+        // SELALU gunakan /C agar terminal AUTO-CLOSE setelah selesai
+        // Pause (kalau perlu) sudah ada di dalam cmd_to_run, TAPI TETAP /C
+        let full_cmd = format!("/C title {} & {}", window_title, cmd_to_run);
+        crate::log::info!("running: cmd.exe {}", full_cmd);
+        
+        let mut cmd_line = to_wide_string(&full_cmd);
+        let current_dir = to_wide_string(&cwd.display().to_string());
+
+        let mut si: STARTUPINFOW = unsafe { std::mem::zeroed() };
+        si.cb = std::mem::size_of::<STARTUPINFOW>() as DWORD;
+
+        let mut pi: PROCESS_INFORMATION = unsafe { std::mem::zeroed() };
+
+        let result = unsafe {
+            CreateProcessW(
+                cmd_path.as_ptr(),
+                cmd_line.as_mut_ptr(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+                0,
+                CREATE_NEW_CONSOLE,
+                ptr::null_mut(),
+                current_dir.as_ptr(),
+                &mut si,
+                &mut pi,
+            )
+        };
+
+        if result == 0 {
+            let error = io::Error::last_os_error();
+            return Err(error);
+        }
+
+        unsafe {
+            CloseHandle(pi.h_thread);
+            CloseHandle(pi.h_process);
+        }
+
+        Ok(())
+    }
+
+    fn run_wt(
+        cmd_to_run: &str,
+        x: i32,
+        y: i32,
+        cols: u32,
+        rows: u32,
+        auto_position: bool,
+        title: Option<&str>,
+        cwd: &Path,
+    ) -> io::Result<()> {
+        use std::process::Command;
+
+        // Windows Terminal command line
+        let mut wt_args = vec![];
+        
+        // Add position and size ONLY if not auto-position
+        // If auto-position, don't specify --pos or --size, let wt decide
+        if !auto_position {
+            wt_args.push("--pos".to_string());
+            wt_args.push(format!("{},{}", x, y));
+            wt_args.push("--size".to_string());
+            wt_args.push(format!("{},{}", cols, rows));
+        }
+        
+        wt_args.push("--title".to_string());
+        wt_args.push(title.unwrap_or("rund").to_string());
+        wt_args.push("-d".to_string());
+        wt_args.push(cwd.display().to_string());
+        wt_args.push("cmd.exe".to_string());
+        wt_args.push("/C".to_string());
+        wt_args.push(cmd_to_run.to_string());
+
+        crate::log::info!("running: wt.exe {:?}", wt_args);
+        Command::new("wt.exe").args(&wt_args).current_dir(cwd).spawn().map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::Other,
+                format!("Failed to launch Windows Terminal: {}", e),
+            )
+        })?;
+
+        Ok(())
+    }
+}
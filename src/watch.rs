@@ -0,0 +1,420 @@
+// src/watch.rs - `rund --watch`: runs in the popup and re-runs the command
+// whenever a file matching the globs changes. inotify on Linux, comparing
+// modification times elsewhere; files git ignores never count.
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::glob::{Glob, Ignore};
+
+// Changes closer together than this are one change: editors often write a
+// file in several steps, and a checkout touches many files at once
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+struct Watched {
+    globs: Vec<Glob>,
+    ignore: Ignore,
+}
+
+impl Watched {
+    // A file (not a directory) that matches and is not ignored
+    fn wants(&self, path: &Path) -> bool {
+        self.globs.iter().any(|g| g.matches(path)) && !self.ignore.is_ignored(path, false)
+    }
+
+    // Every directory that can hold matching files, skipping ignored ones,
+    // along with the matching files in them and their modification times
+    fn walk(&mut self, dir: &Path, dirs: &mut Vec<PathBuf>, files: &mut HashMap<PathBuf, SystemTime>) {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(_) => return,
+        };
+        self.ignore.visit(dir);
+        dirs.push(dir.to_path_buf());
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let is_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+            if is_dir {
+                if !self.ignore.is_ignored(&path, true) {
+                    self.walk(&path, dirs, files);
+                }
+            } else if self.wants(&path) {
+                let modified = entry.metadata().and_then(|m| m.modified()).unwrap_or(SystemTime::UNIX_EPOCH);
+                files.insert(path, modified);
+            }
+        }
+    }
+
+    // Directories and files below every glob's base
+    fn scan(&mut self) -> (Vec<PathBuf>, HashMap<PathBuf, SystemTime>) {
+        let mut dirs = Vec::new();
+        let mut files = HashMap::new();
+        let mut bases: Vec<PathBuf> = self.globs.iter().map(|g| g.base.clone()).collect();
+        bases.sort();
+        bases.dedup();
+        for base in bases {
+            // Nested bases are walked with their parent
+            if dirs.iter().any(|d| base.starts_with(d)) {
+                continue;
+            }
+            self.walk(&base, &mut dirs, &mut files);
+        }
+        (dirs, files)
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod inotify {
+    use std::ffi::{c_char, c_ulong, CString};
+    use std::fs::File;
+    use std::io::{self, Read};
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::io::{AsRawFd, FromRawFd};
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+
+    const IN_CLOEXEC: i32 = 0o2000000;
+    const IN_CLOSE_WRITE: u32 = 0x8;
+    const IN_MOVED_FROM: u32 = 0x40;
+    const IN_MOVED_TO: u32 = 0x80;
+    const IN_CREATE: u32 = 0x100;
+    const IN_DELETE: u32 = 0x200;
+    const IN_Q_OVERFLOW: u32 = 0x4000;
+    const IN_ISDIR: u32 = 0x40000000;
+    const POLLIN: i16 = 1;
+
+    #[repr(C)]
+    struct PollFd {
+        fd: i32,
+        events: i16,
+        revents: i16,
+    }
+
+    extern "C" {
+        fn inotify_init1(flags: i32) -> i32;
+        fn inotify_add_watch(fd: i32, path: *const c_char, mask: u32) -> i32;
+        fn poll(fds: *mut PollFd, nfds: c_ulong, timeout: i32) -> i32;
+    }
+
+    pub enum Event {
+        Changed { path: PathBuf, is_dir: bool },
+        // Events were lost: everything may have changed
+        Overflow,
+    }
+
+    pub struct Watcher {
+        file: File,
+        // Watch descriptor -> directory
+        dirs: Vec<(i32, PathBuf)>,
+    }
+
+    impl Watcher {
+        pub fn new() -> io::Result<Watcher> {
+            let fd = unsafe { inotify_init1(IN_CLOEXEC) };
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let file = unsafe { File::from_raw_fd(fd) };
+            Ok(Watcher { file, dirs: Vec::new() })
+        }
+
+        pub fn add(&mut self, dir: &Path) -> io::Result<()> {
+            let path = CString::new(dir.as_os_str().as_bytes())?;
+            let mask = IN_CLOSE_WRITE | IN_MOVED_FROM | IN_MOVED_TO | IN_CREATE | IN_DELETE;
+            let wd = unsafe { inotify_add_watch(self.file.as_raw_fd(), path.as_ptr(), mask) };
+            if wd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            if !self.dirs.iter().any(|(w, _)| *w == wd) {
+                self.dirs.push((wd, dir.to_path_buf()));
+            }
+            Ok(())
+        }
+
+        // Whether events arrived within `timeout` (None = wait for them)
+        pub fn wait(&self, timeout: Option<Duration>) -> io::Result<bool> {
+            let mut fds = PollFd { fd: self.file.as_raw_fd(), events: POLLIN, revents: 0 };
+            let timeout = timeout.map_or(-1, |t| t.as_millis() as i32);
+            loop {
+                match unsafe { poll(&mut fds, 1, timeout) } {
+                    n if n >= 0 => return Ok(n > 0),
+                    _ => {
+                        let e = io::Error::last_os_error();
+                        if e.kind() != io::ErrorKind::Interrupted {
+                            return Err(e);
+                        }
+                    }
+                }
+            }
+        }
+
+        // The events that are ready; call after wait() said there are some
+        pub fn read(&mut self) -> io::Result<Vec<Event>> {
+            let mut buf = vec![0u8; 64 * 1024];
+            let n = self.file.read(&mut buf)?;
+            let mut events = Vec::new();
+            let mut pos = 0;
+            // struct inotify_event { int wd; u32 mask; u32 cookie; u32 len; char name[len]; }
+            while pos + 16 <= n {
+                let field = |at: usize| u32::from_ne_bytes([buf[at], buf[at + 1], buf[at + 2], buf[at + 3]]);
+                let wd = field(pos) as i32;
+                let mask = field(pos + 4);
+                let len = field(pos + 12) as usize;
+                let name = &buf[pos + 16..(pos + 16 + len).min(n)];
+                let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+                pos += 16 + len;
+
+                if mask & IN_Q_OVERFLOW != 0 {
+                    events.push(Event::Overflow);
+                } else if let Some((_, dir)) = self.dirs.iter().find(|(w, _)| *w == wd) {
+                    if !name.is_empty() {
+                        events.push(Event::Changed {
+                            path: dir.join(std::ffi::OsStr::from_bytes(name)),
+                            is_dir: mask & IN_ISDIR != 0,
+                        });
+                    }
+                }
+            }
+            Ok(events)
+        }
+    }
+}
+
+// Blocks until a matching file changed and the changes have settled
+#[cfg(target_os = "linux")]
+struct Changes {
+    watched: Watched,
+    watcher: inotify::Watcher,
+}
+
+#[cfg(target_os = "linux")]
+impl Changes {
+    fn new(watched: Watched) -> io::Result<Changes> {
+        let mut changes = Changes { watched, watcher: inotify::Watcher::new()? };
+        changes.watch_all()?;
+        Ok(changes)
+    }
+
+    fn watch_all(&mut self) -> io::Result<()> {
+        let (dirs, _) = self.watched.scan();
+        for dir in dirs {
+            self.watcher.add(&dir)?;
+        }
+        Ok(())
+    }
+
+    // Whether any of the ready events is a change that counts; new
+    // directories are watched as they appear
+    fn relevant(&mut self) -> io::Result<bool> {
+        let mut relevant = false;
+        for event in self.watcher.read()? {
+            match event {
+                inotify::Event::Overflow => {
+                    self.watch_all()?;
+                    relevant = true;
+                }
+                inotify::Event::Changed { path, is_dir: true } => {
+                    if !self.watched.ignore.is_ignored(&path, true) {
+                        let (mut dirs, mut files) = (Vec::new(), HashMap::new());
+                        self.watched.walk(&path, &mut dirs, &mut files);
+                        for dir in dirs {
+                            // Gone again already: nothing to watch
+                            self.watcher.add(&dir).ok();
+                        }
+                        relevant |= !files.is_empty();
+                    }
+                }
+                inotify::Event::Changed { path, is_dir: false } => {
+                    relevant |= self.watched.wants(&path);
+                }
+            }
+        }
+        Ok(relevant)
+    }
+
+    fn wait(&mut self) -> io::Result<()> {
+        // What happened while the command ran counts too: it is still queued
+        while !(self.watcher.wait(None)? && self.relevant()?) {}
+        // Settle: wait until no event arrived for DEBOUNCE
+        while self.watcher.wait(Some(DEBOUNCE))? {
+            self.relevant()?;
+        }
+        Ok(())
+    }
+}
+
+// Elsewhere the same by comparing scans; also built on Linux for the tests
+#[cfg(any(not(target_os = "linux"), test))]
+struct Polling {
+    watched: Watched,
+    files: HashMap<PathBuf, SystemTime>,
+}
+
+#[cfg(not(target_os = "linux"))]
+type Changes = Polling;
+
+#[cfg(any(not(target_os = "linux"), test))]
+impl Polling {
+    fn new(mut watched: Watched) -> io::Result<Polling> {
+        let (_, files) = watched.scan();
+        Ok(Polling { watched, files })
+    }
+
+    fn wait(&mut self) -> io::Result<()> {
+        const INTERVAL: Duration = Duration::from_millis(500);
+        loop {
+            std::thread::sleep(INTERVAL);
+            let (_, files) = self.watched.scan();
+            if files != self.files {
+                self.files = files;
+                // Settle: wait until a scan finds nothing new
+                loop {
+                    std::thread::sleep(DEBOUNCE);
+                    let (_, files) = self.watched.scan();
+                    if files == self.files {
+                        return Ok(());
+                    }
+                    self.files = files;
+                }
+            }
+        }
+    }
+}
+
+// Blocks until one file is written, replaced or removed, for the daemon's
+// config. Only the file's own directory is watched, not below it.
+#[cfg(not(target_os = "windows"))]
+pub struct FileChanges {
+    path: PathBuf,
+    #[cfg(target_os = "linux")]
+    watcher: inotify::Watcher,
+    #[cfg(not(target_os = "linux"))]
+    modified: Option<SystemTime>,
+}
+
+#[cfg(not(target_os = "windows"))]
+impl FileChanges {
+    pub fn new(path: &Path) -> io::Result<FileChanges> {
+        #[cfg(target_os = "linux")]
+        {
+            let mut watcher = inotify::Watcher::new()?;
+            watcher.add(path.parent().unwrap_or(Path::new(".")))?;
+            Ok(FileChanges { path: path.to_path_buf(), watcher })
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+            Ok(FileChanges { path: path.to_path_buf(), modified })
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn wait(&mut self) -> io::Result<()> {
+        let path = &self.path;
+        let ours = |event: &inotify::Event| match event {
+            inotify::Event::Changed { path: changed, .. } => changed == path,
+            inotify::Event::Overflow => true,
+        };
+        while !(self.watcher.wait(None)? && self.watcher.read()?.iter().any(ours)) {}
+        while self.watcher.wait(Some(DEBOUNCE))? {
+            self.watcher.read()?;
+        }
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn wait(&mut self) -> io::Result<()> {
+        const INTERVAL: Duration = Duration::from_millis(500);
+        let modified = || fs::metadata(&self.path).and_then(|m| m.modified()).ok();
+        loop {
+            std::thread::sleep(INTERVAL);
+            let now = modified();
+            if now != self.modified {
+                self.modified = now;
+                std::thread::sleep(DEBOUNCE);
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn clear_screen() {
+    if cfg!(target_os = "windows") {
+        Command::new("cmd").args(["/C", "cls"]).status().ok();
+    } else {
+        print!("\x1b[2J\x1b[3J\x1b[H");
+        io::stdout().flush().ok();
+    }
+}
+
+// The popup's loop: run `argv`, show how it went, wait for a change, clear
+// the screen and run it again, until the window is closed or Ctrl+C
+pub fn run(patterns: &[String], argv: &[String]) -> io::Result<()> {
+    let (program, args) = argv
+        .split_first()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Nothing to run"))?;
+    let cwd = env::current_dir()?;
+    let globs: Vec<Glob> = patterns.iter().map(|p| Glob::new(p, &cwd)).collect();
+    let ignore = Ignore::for_dir(&cwd);
+    let mut changes = Changes::new(Watched { globs, ignore })?;
+
+    loop {
+        clear_screen();
+        let started = Instant::now();
+        let status = Command::new(program).args(args).status();
+        let took = crate::notify::duration(started.elapsed().as_secs());
+        let outcome = match status {
+            Ok(status) => match status.code() {
+                Some(code) => format!("exit code {} after {}", code, took),
+                None => format!("killed after {}", took),
+            },
+            Err(e) => format!("could not run {}: {}", program, e),
+        };
+        println!();
+        println!("── {} · watching {} · Ctrl+C to stop", outcome, patterns.join(" "));
+        io::stdout().flush().ok();
+
+        changes.wait()?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changes_close_together_are_one() {
+        let dir = env::temp_dir().join(format!("rund-test-watch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("ignored.log"), "").unwrap();
+        let watched = Watched { globs: vec![Glob::new("*.txt", &dir)], ignore: Ignore::default() };
+        let mut changes = Polling::new(watched).unwrap();
+        assert!(changes.files.is_empty());
+
+        // A burst of writes, each well within DEBOUNCE of the one before
+        let writer = {
+            let dir = dir.clone();
+            std::thread::spawn(move || {
+                for i in 0..12 {
+                    fs::write(dir.join(format!("{}.txt", i)), "x").unwrap();
+                    fs::write(dir.join(format!("{}.log", i)), "x").unwrap();
+                    std::thread::sleep(Duration::from_millis(50));
+                }
+                Instant::now()
+            })
+        };
+        changes.wait().unwrap();
+        let woke = Instant::now();
+        let last_write = writer.join().unwrap();
+
+        // One wake-up, after the burst settled, that took all of it in
+        assert!(woke >= last_write, "woke up before the writes stopped");
+        assert_eq!(changes.files.len(), 12);
+        assert_eq!(changes.watched.scan().1, changes.files);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
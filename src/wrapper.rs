@@ -0,0 +1,306 @@
+// src/wrapper.rs - The script that runs in the popup around the command:
+// pausing as planned, tracking the window in the session registry and
+// calling back into rund as it exits. POSIX sh everywhere but Windows, where
+// it is a batch file for cmd.
+#[cfg(target_os = "windows")]
+use std::io;
+use std::path::PathBuf;
+
+use crate::config::PauseBehavior;
+use crate::environment;
+
+// Pause behavior resolved for one launch, as handed to the wrapper generators
+#[derive(Debug, Clone)]
+pub(crate) struct PausePlan {
+    pub(crate) behavior: PauseBehavior,
+    pub(crate) fast_exit_secs: u64,
+    pub(crate) timeout: Option<u64>,
+    // Set when the wrapper should record how quickly the prompt was dismissed
+    pub(crate) learn: Option<PauseLearning>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct PauseLearning {
+    pub(crate) exe: String,
+    pub(crate) state_file: PathBuf,
+}
+
+// Registry entry of a launched popup, for rund ps/kill/focus and placement
+// of the ones after it
+#[derive(Debug, Clone)]
+pub(crate) struct WindowTracking {
+    pub(crate) id: String,
+    pub(crate) file: PathBuf,
+    // Gets the command's exit code (see runtime::exit_file)
+    pub(crate) exit_file: PathBuf,
+    // Variable holding the terminal's window (or tmux pane) id, if it sets one
+    #[cfg(not(target_os = "windows"))]
+    pub(crate) window_var: Option<&'static str>,
+}
+
+// Extra work the generated wrapper does around the command
+#[derive(Debug, Default)]
+pub(crate) struct WrapperHooks {
+    // Variables set up before the command runs
+    pub(crate) env: environment::Environment,
+    pub(crate) tracking: Option<WindowTracking>,
+    // Programs (argv) run as the wrapper exits, while the window still exists;
+    // an EXIT_CODE_ARG argument becomes the command's exit code
+    pub(crate) on_exit: Vec<Vec<String>>,
+}
+
+pub(crate) const EXIT_CODE_ARG: &str = "{exit_code}";
+
+// Wrap a command line for a POSIX shell so the script itself applies the
+// resolved pause behavior once the command has exited. Only POSIX features
+// are used, so sh, bash and zsh all run it.
+#[cfg(not(target_os = "windows"))]
+pub(crate) fn sh_wrapper(command: &str, pause: &PausePlan) -> String {
+    let wait = "printf 'Press Enter to exit...'; read rund_x";
+    // With a timeout, count down and close unless a key is pressed, which then
+    // falls back to the regular prompt so the output stays up. The terminal
+    // reads single keys with a one second timeout meanwhile.
+    let prompt = match pause.timeout {
+        Some(secs) => format!(
+            "echo; rund_t={}; rund_keep=; rund_tty=$(stty -g); stty -icanon -echo min 0 time 10; \
+             while [ $rund_t -gt 0 ]; do \
+             printf '\\rClosing in %2ds - press any key to keep open ' $rund_t; \
+             if [ -n \"$(dd bs=1 count=1 2>/dev/null | od -An -c)\" ]; then rund_keep=1; break; fi; \
+             rund_t=$((rund_t - 1)); done; stty \"$rund_tty\"; \
+             if [ -n \"$rund_keep\" ]; then echo; {}; fi",
+            secs, wait
+        ),
+        None => wait.to_string(),
+    };
+
+    // Record whether the prompt was dismissed within a second (see learned_pause)
+    let prompt = match pause.learn {
+        Some(ref learn) => format!(
+            "rund_p=$(date +%s); {}; \
+             if [ $(($(date +%s) - rund_p)) -le 1 ]; then rund_q=1; else rund_q=0; fi; \
+             printf '%s\\t%s\\n' '{}' $rund_q >> '{}'",
+            prompt,
+            learn.exe,
+            learn.state_file.display().to_string().replace('\'', "'\\''")
+        ),
+        None => prompt,
+    };
+
+    match pause.behavior {
+        PauseBehavior::Never | PauseBehavior::Auto => command.to_string(),
+        // Keep the command's exit code for the exit hooks
+        PauseBehavior::Always => format!("{}; rund_rc=$?; {}; exit $rund_rc", command, prompt),
+        PauseBehavior::OnError => format!(
+            "{}; rund_rc=$?; if [ $rund_rc -ne 0 ]; then echo; echo \"[exit code $rund_rc]\"; {}; fi; exit $rund_rc",
+            command, prompt
+        ),
+        PauseBehavior::OnErrorOrFast => format!(
+            "rund_start=$(date +%s); {}; rund_rc=$?; \
+             if [ $rund_rc -ne 0 ] || [ $(($(date +%s) - rund_start)) -lt {} ]; then echo; echo \"[exit code $rund_rc]\"; {}; fi; exit $rund_rc",
+            command, pause.fast_exit_secs, prompt
+        ),
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+pub(crate) fn sh_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+// Set up the environment, let the window registry know the wrapper's PID on
+// start and when it exits, and run the exit hooks from a function trapped on EXIT
+#[cfg(not(target_os = "windows"))]
+pub(crate) fn sh_hooked(script: &str, hooks: &WrapperHooks) -> String {
+    let mut start = hooks.env.sh_script(sh_quote);
+    let mut on_exit = Vec::new();
+    if let Some(ref tracking) = hooks.tracking {
+        let file = sh_quote(&tracking.file.display().to_string());
+        start.push_str(&format!("printf 'pid\\t%s\\t%s\\n' '{}' $$ >> {}; ", tracking.id, file));
+        if let Some(var) = tracking.window_var {
+            start.push_str(&format!(
+                "if [ -n \"${var}\" ]; then printf 'window\\t%s\\t%s\\n' '{id}' \"${var}\" >> {file}; fi; ",
+                var = var,
+                id = tracking.id,
+                file = file
+            ));
+        }
+        on_exit.push(format!(
+            "printf '%s\\n' \"$rund_status\" > {}",
+            sh_quote(&tracking.exit_file.display().to_string())
+        ));
+        on_exit.push(format!("printf 'close\\t%s\\t%s\\n' '{}' \"$rund_status\" >> {}", tracking.id, file));
+    }
+    for argv in &hooks.on_exit {
+        let args: Vec<String> = argv
+            .iter()
+            .map(|arg| if arg == EXIT_CODE_ARG { "\"$rund_status\"".to_string() } else { sh_quote(arg) })
+            .collect();
+        on_exit.push(args.join(" "));
+    }
+
+    if on_exit.is_empty() {
+        return format!("{}{}", start, script);
+    }
+    // $? on entry to an EXIT trap is the status the shell exits with
+    format!(
+        "rund_exit() {{ rund_status=$?; {}; }}; trap rund_exit EXIT; {}{}",
+        on_exit.join("; "),
+        start,
+        script
+    )
+}
+
+// A command line as a batch file line that means what it did after `cmd /C`:
+// %NAME% references still expand, any other % (in "100%.txt") is doubled so
+// the batch file keeps it
+#[cfg(any(target_os = "windows", test))]
+fn batch_line(command: &str) -> String {
+    let mut line = String::new();
+    let mut rest = command;
+    while let Some(start) = rest.find('%') {
+        line.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after.find('%') {
+            Some(end) if end > 0 && !after[..end].contains(char::is_whitespace) => {
+                line.push_str(&rest[start..start + end + 2]);
+                rest = &after[end + 1..];
+            }
+            _ => {
+                line.push_str("%%");
+                rest = after;
+            }
+        }
+    }
+    line.push_str(rest);
+    line
+}
+
+// Build what follows `cmd.exe /C`. Exit-code-aware modes need %errorlevel%
+// expanded after the command ran, which a single /C line cannot do, so they
+// (and the auto-close countdown) go through a self-deleting batch file instead.
+#[cfg(target_os = "windows")]
+pub(crate) fn cmd_wrapper(command: &str, pause: &PausePlan, hooks: &WrapperHooks) -> io::Result<String> {
+    // `cmd /C` drops the outer quotes of a line that starts with one, as a
+    // shell called by its quoted name would, so those go to a batch file too
+    let inline = hooks.env.is_empty()
+        && hooks.tracking.is_none()
+        && hooks.on_exit.is_empty()
+        && !command.starts_with('"');
+    let (pauses, on_error, fast) = match pause.behavior {
+        PauseBehavior::Always if inline && pause.timeout.is_none() && pause.learn.is_none() => {
+            return Ok(format!("{} & pause", command));
+        }
+        PauseBehavior::Never | PauseBehavior::Auto if inline => return Ok(command.to_string()),
+        PauseBehavior::Never | PauseBehavior::Auto => (false, false, false),
+        PauseBehavior::Always => (true, false, false),
+        PauseBehavior::OnError => (true, true, false),
+        PauseBehavior::OnErrorOrFast => (true, true, true),
+    };
+
+    // Seconds since midnight, %time% padded so "9:05" parses like "09:05"
+    let now = |var: &str| {
+        format!(
+            "for /f \"tokens=1-3 delims=:.,\" %%a in (\"%time: =0%\") do set /a {}=(1%%a-100)*3600+(1%%b-100)*60+(1%%c-100)\r\n",
+            var
+        )
+    };
+
+    let mut script = String::from("@echo off\r\n");
+    for line in hooks.env.cmd_lines() {
+        script.push_str(&line);
+        script.push_str("\r\n");
+    }
+    // Report the PID of the cmd.exe hosting the window: the parent of the
+    // `for /f` child shell that runs PowerShell
+    if let Some(ref tracking) = hooks.tracking {
+        script.push_str(&format!(
+            "for /f %%p in ('powershell -NoProfile -Command \"$p = (Get-CimInstance Win32_Process -Filter ('ProcessId=' + $PID)).ParentProcessId; \
+             (Get-CimInstance Win32_Process -Filter ('ProcessId=' + $p)).ParentProcessId\"') do >>\"{}\" echo pid\t{}\t%%p\r\n",
+            tracking.file.display(),
+            tracking.id
+        ));
+    }
+    if fast {
+        script.push_str(&now("rund_start"));
+    }
+    script.push_str(&batch_line(command));
+    script.push_str("\r\nset rund_rc=%errorlevel%\r\n");
+    if !pauses {
+        script.push_str("goto rund_done\r\n");
+    }
+    if on_error {
+        script.push_str("if \"%rund_rc%\"==\"0\" goto rund_check_fast\r\n");
+        script.push_str("echo.\r\necho [exit code %rund_rc%]\r\ngoto rund_pause\r\n");
+        script.push_str(":rund_check_fast\r\n");
+        if fast {
+            script.push_str(&now("rund_end"));
+            script.push_str("set /a rund_elapsed=rund_end-rund_start\r\n");
+            script.push_str("if %rund_elapsed% lss 0 set /a rund_elapsed+=86400\r\n");
+            script.push_str(&format!("if %rund_elapsed% lss {} goto rund_pause\r\n", pause.fast_exit_secs));
+        }
+        script.push_str("goto rund_done\r\n");
+    }
+    script.push_str(":rund_pause\r\n");
+    if pause.learn.is_some() {
+        script.push_str(&now("rund_p"));
+    }
+    match pause.timeout {
+        // PowerShell polls the console so any key (not just the ones `choice`
+        // accepts) cancels the countdown; exit 1 means "keep the window open"
+        Some(secs) => script.push_str(&format!(
+            "powershell -NoProfile -Command \"for ($i = {}; $i -gt 0; $i--) {{ \
+             if ($i %% 10 -eq 0) {{ [Console]::Write(('{{0}}Closing in {{1,2}}s - press any key to keep open ' -f [char]13, ($i / 10))) }}; \
+             if ([Console]::KeyAvailable) {{ [void][Console]::ReadKey($true); exit 1 }}; \
+             Start-Sleep -Milliseconds 100 }}; exit 0\"\r\n\
+             echo.\r\nif errorlevel 1 pause\r\n",
+            secs * 10
+        )),
+        None => script.push_str("pause\r\n"),
+    }
+    // Record whether the prompt was dismissed within a second (see learned_pause)
+    if let Some(ref learn) = pause.learn {
+        script.push_str(&now("rund_p_end"));
+        script.push_str("set /a rund_waited=rund_p_end-rund_p\r\n");
+        script.push_str("set rund_q=0\r\nif %rund_waited% leq 1 set rund_q=1\r\n");
+        script.push_str(&format!(">>\"{}\" echo {}\t%rund_q%\r\n", learn.state_file.display(), learn.exe));
+    }
+    script.push_str(":rund_done\r\n");
+    for argv in &hooks.on_exit {
+        let line: Vec<String> = argv
+            .iter()
+            .map(|arg| match arg.as_str() {
+                EXIT_CODE_ARG => "%rund_rc%".to_string(),
+                arg => format!("\"{}\"", arg.replace('%', "%%")),
+            })
+            .collect();
+        script.push_str(&line.join(" "));
+        script.push_str("\r\n");
+    }
+    if let Some(ref tracking) = hooks.tracking {
+        script.push_str(&format!(">\"{}\" echo %rund_rc%\r\n", tracking.exit_file.display()));
+        script.push_str(&format!(">>\"{}\" echo close\t{}\t%rund_rc%\r\n", tracking.file.display(), tracking.id));
+    }
+    script.push_str("(goto) 2>nul & del \"%~f0\" & exit /b %rund_rc%\r\n");
+
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let script_path = std::env::temp_dir().join(format!("rund_wrap_{}.cmd", nanos));
+    std::fs::write(&script_path, script)?;
+
+    Ok(format!("call \"{}\"", script_path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn batch_lines_keep_variables() {
+        assert_eq!(batch_line("echo %USERPROFILE%"), "echo %USERPROFILE%");
+        assert_eq!(batch_line("type \"C:\\100%.txt\""), "type \"C:\\100%%.txt\"");
+        assert_eq!(batch_line("echo 50% of %PATH% and 60%"), "echo 50%% of %PATH% and 60%%");
+        assert_eq!(batch_line("echo %% %"), "echo %%%% %%");
+    }
+}